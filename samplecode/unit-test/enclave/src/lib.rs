@@ -38,6 +38,7 @@ extern crate sgx_alloc;
 extern crate sgx_rand;
 extern crate sgx_trts;
 extern crate sgx_tseal;
+extern crate sgx_tse;
 #[macro_use]
 extern crate memoffset;
 extern crate sgx_serialize;
@@ -59,6 +60,9 @@ mod utils;
 mod test_crypto;
 use test_crypto::*;
 
+mod test_keys;
+use test_keys::*;
+
 mod test_assert;
 use test_assert::*;
 
@@ -68,6 +72,9 @@ use test_rts::*;
 mod test_seal;
 use test_seal::*;
 
+mod test_se;
+use test_se::*;
+
 mod test_rand;
 use test_rand::*;
 
@@ -118,6 +125,33 @@ pub extern "C" fn test_main_entrance() -> size_t {
         // tcrypto
         test_rsgx_sha256_slice,
         test_rsgx_sha256_handle,
+        test_rsgx_sha256_handle_reset,
+        test_rsgx_sha384_sha512,
+        test_rsgx_hkdf_sha256,
+        test_rsgx_aes_gcm_dec_handle,
+        test_rsgx_cmac_handle_chunked,
+        test_rsgx_rijndael128_cmac_verify,
+        test_rsgx_rijndael128_cmac_subkeys,
+        test_rsgx_hmac_sha256_varkey,
+        test_rsgx_ct_compare,
+        test_rsgx_ct_select,
+        test_rsgx_ghash,
+        test_rsgx_aes_block_encrypt,
+        test_rsgx_kbkdf_cmac_counter,
+        test_rsgx_aes_ctr_seek,
+        test_rsgx_rsa_wrap_key,
+        test_rsgx_aes_siv,
+        test_rsgx_pbkdf2_hmac_sha256,
+        test_rsgx_aes_gcm_siv,
+        test_rsgx_aes_gcm_siv_failures,
+        test_rsgx_x25519,
+        test_rsgx_key128_ct_eq,
+        test_rsgx_pfs_derive_key,
+        test_rsgx_pfs_data_key,
+        test_rsgx_pfs_derive_keys_random,
+        test_rsgx_pfs_derive_key_random,
+        test_sgx_pfs_kdf_input_layout,
+        test_rsgx_pfs_derive_key_in_domain,
         // assert
         foo_panic,
         foo_should,
@@ -155,18 +189,38 @@ pub extern "C" fn test_main_entrance() -> size_t {
         test_array_sealing,  // Thanks to @silvanegli
         test_mac_aadata_slice,
         test_mac_aadata_number,
+        test_unseal_with_aad,
+        // tse
+        test_key_request_builder,
+        test_key_request_builder_key_names,
+        test_key_request_layout,
+        test_self_svn,
+        test_self_report_ref,
+        test_targeted_report,
+        test_cpu_svn_is_at_least,
+        test_key_id_cpu_svn_conversions,
         // rand
         test_rand_os_sgxrng,
+        test_rand_os_fill_contiguous,
         test_rand_distributions,
         test_rand_isaac_isaacrng,
         test_rand_chacharng,
         test_rand_reseeding,
+        test_rand_chacha_sgx_reseeding,
+        test_rand_gen_range_unbiased,
+        test_rand_secure_random_bytes,
+        test_rand_callback_rng,
         // serialize
         test_serialize_base,
         test_serialize_struct,
         test_serialize_enum,
         // std::sgxfs
         test_sgxfs,
+        test_sgxfs_header,
+        test_sgxfs_validate,
+        test_sgxfs_len,
+        test_sgxfs_drop_hook,
+        test_sgxfs_write_through,
         // std::fs
         test_fs,
         // std::fs untrusted mode
@@ -178,6 +232,9 @@ pub extern "C" fn test_main_entrance() -> size_t {
         // types
         check_metadata_size,
         check_version,
+        check_key_types_try_from,
+        check_secret_redacts_debug,
+        check_secret_zeroed_on_drop,
         // env
         test_env_vars_os,
         test_env_self_exe_path,
@@ -16,6 +16,7 @@
 // under the License..
 
 use sgx_rand::{Rng, StdRng};
+use sgx_types::sgx_key_128bit_t;
 use std::io::{Read, Write};
 use std::sgxfs::{self, SgxFile};
 use std::string::*;
@@ -66,6 +67,51 @@ pub fn test_sgxfs() {
         let opt = SgxFile::open("?");
         assert_eq!(opt.is_err(), true);
     }
+    {
+        let zero_key: sgx_key_128bit_t = [0; 16];
+        let opt = SgxFile::create_ex("sgx_file_zero_key", &zero_key);
+        assert_eq!(opt.is_err(), true);
+        let opt = sgxfs::OpenOptions::new().append(true).open_ex("sgx_file_zero_key", &zero_key);
+        assert_eq!(opt.is_err(), true);
+
+        // A file written with an all-zero key before the check existed.
+        let mut opt = sgxfs::OpenOptions::new()
+            .write(true)
+            .allow_weak_key(true)
+            .open_ex("sgx_file_zero_key", &zero_key)
+            .unwrap();
+        assert_eq!(opt.write_all(b"legacy").is_ok(), true);
+        drop(opt);
+        let mut data = std::vec::Vec::new();
+        let mut opt = SgxFile::open_ex("sgx_file_zero_key", &zero_key).unwrap();
+        assert_eq!(opt.read_to_end(&mut data).is_ok(), true);
+        assert_eq!(data, b"legacy");
+        drop(opt);
+        let opt = sgxfs::OpenOptions::new().append(true).open_ex("sgx_file_zero_key", &zero_key);
+        assert_eq!(opt.is_err(), true);
+        let opt = sgxfs::OpenOptions::new()
+            .read(true)
+            .update(true)
+            .open_ex("sgx_file_zero_key", &zero_key);
+        assert_eq!(opt.is_err(), true);
+        let opt = sgxfs::OpenOptions::new()
+            .append(true)
+            .allow_weak_key(true)
+            .open_ex("sgx_file_zero_key", &zero_key);
+        assert_eq!(opt.is_ok(), true);
+        drop(opt);
+        let result = sgxfs::remove("sgx_file_zero_key");
+        assert_eq!(result.is_ok(), true);
+
+        let mut key: sgx_key_128bit_t = [0; 16];
+        let mut rand = StdRng::new().unwrap();
+        rand.fill_bytes(&mut key);
+        let opt = SgxFile::create_ex("sgx_file_user_key", &key);
+        assert_eq!(opt.is_ok(), true);
+        drop(opt);
+        let result = sgxfs::remove("sgx_file_user_key");
+        assert_eq!(result.is_ok(), true);
+    }
     #[cfg(feature = "hw_test")]
     {
         let opt1 = SgxFile::open("/dev/isgx");
@@ -86,6 +132,245 @@ pub fn test_sgxfs() {
     }
 }
 
+pub fn test_sgxfs_header() {
+    use sgx_tse::rsgx_self_svn;
+    use std::io::ErrorKind;
+    use std::sgxfs::SgxFileHeader;
+
+    {
+        let mut file = SgxFile::create("sgx_file_header_auto").unwrap();
+        file.write_all(b"header test").unwrap();
+    }
+    let header = SgxFileHeader::read("sgx_file_header_auto").unwrap();
+    assert_eq!(header.version().0, 1);
+    assert_eq!(header.is_user_key(), false);
+    assert!(header.key_id().id.iter().any(|b| *b != 0));
+    let (cpu_svn, isv_svn) = rsgx_self_svn();
+    assert_eq!(header.cpu_svn().svn, cpu_svn.svn);
+    assert_eq!(header.isv_svn(), isv_svn);
+
+    let mut key: sgx_key_128bit_t = [0; 16];
+    StdRng::new().unwrap().fill_bytes(&mut key);
+    drop(SgxFile::create_ex("sgx_file_header_user", &key).unwrap());
+    let header = SgxFile::read_header("sgx_file_header_user").unwrap();
+    assert_eq!(header.is_user_key(), true);
+    assert_eq!(header.is_update_pending(), false);
+
+    let err = SgxFile::open_ex("sgx_file_header_auto", &key).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let err = SgxFile::open("sgx_file_header_user").err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(SgxFile::open_ex("sgx_file_header_user", &key).is_ok());
+    assert!(sgxfs::remove("sgx_file_header_auto").is_ok());
+    assert!(sgxfs::remove("sgx_file_header_user").is_ok());
+
+    {
+        let mut file = File::create("sgx_file_header_plain").unwrap();
+        file.write_all(&[0x55; 128]).unwrap();
+    }
+    let err = SgxFileHeader::read("sgx_file_header_plain").err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(remove_file("sgx_file_header_plain").is_ok());
+
+    {
+        use std::io::{Seek, SeekFrom};
+        use std::untrusted::fs::OpenOptions;
+
+        drop(SgxFile::create("sgx_file_header_version").unwrap());
+        let mut file = OpenOptions::new().write(true).open("sgx_file_header_version").unwrap();
+        file.seek(SeekFrom::Start(8)).unwrap();
+        file.write_all(&[2, 0]).unwrap();
+    }
+    let err = SgxFile::open("sgx_file_header_version").err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(err.to_string().contains("version 2.0"));
+    let err = SgxFileHeader::read("sgx_file_header_version").err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(sgxfs::remove("sgx_file_header_version").is_ok());
+}
+
+pub fn test_sgxfs_validate() {
+    use std::io::{Seek, SeekFrom};
+    use std::sgxfs::OpenOptions as SgxOpenOptions;
+    use std::untrusted::fs::OpenOptions;
+    use std::vec::Vec;
+
+    let data = vec![0x5a_u8; 16 * 1024];
+    {
+        let mut file = SgxFile::create("sgx_file_validate").unwrap();
+        file.write_all(&data).unwrap();
+    }
+    {
+        let mut file = SgxFile::open("sgx_file_validate").unwrap();
+        let mut head = [0_u8; 10];
+        file.read_exact(&mut head).unwrap();
+        assert!(file.validate().is_ok());
+        assert_eq!(file.seek(SeekFrom::Current(0)).unwrap(), 10);
+
+        let mut exported: Vec<u8> = Vec::new();
+        assert_eq!(file.export_to(&mut exported).unwrap(), data.len() as u64);
+        assert_eq!(exported, data);
+        assert_eq!(file.seek(SeekFrom::Current(0)).unwrap(), 10);
+    }
+    {
+        let mut file = SgxOpenOptions::new()
+            .read(true)
+            .verify_on_open(true)
+            .open("sgx_file_validate")
+            .unwrap();
+        assert_eq!(file.seek(SeekFrom::Current(0)).unwrap(), 0);
+    }
+    {
+        // Clearing the cache would write this back, so validate refuses.
+        let mut file = SgxOpenOptions::new()
+            .read(true)
+            .update(true)
+            .open("sgx_file_validate")
+            .unwrap();
+        file.write_all(&[0xa5; 16]).unwrap();
+        let err = file.validate().err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+    {
+        let mut file = OpenOptions::new().write(true).open("sgx_file_validate").unwrap();
+        file.seek(SeekFrom::Start(3 * 4096 + 100)).unwrap();
+        file.write_all(&[0xff; 16]).unwrap();
+    }
+    {
+        // Physical node 3 is data node 1, which starts at offset 3072 + 4096.
+        let mut file = SgxFile::open("sgx_file_validate").unwrap();
+        let err = file.validate().err().unwrap().to_string();
+        assert!(err.contains("node 3 (offset 7168)"));
+    }
+    {
+        // Only the verified nodes in front of the bad one reach the sink.
+        let mut file = SgxFile::open("sgx_file_validate").unwrap();
+        let mut exported: Vec<u8> = Vec::new();
+        let err = file.export_to(&mut exported).err().unwrap().to_string();
+        assert!(err.contains("node 3 (offset 7168)"));
+        assert_eq!(exported.len(), 7168);
+    }
+    assert!(SgxOpenOptions::new()
+        .read(true)
+        .verify_on_open(true)
+        .open("sgx_file_validate")
+        .is_err());
+    assert!(sgxfs::remove("sgx_file_validate").is_ok());
+}
+
+pub fn test_sgxfs_len() {
+    use std::io::{Seek, SeekFrom};
+
+    {
+        let file = SgxFile::create("sgx_file_len").unwrap();
+        assert_eq!(file.logical_len().unwrap(), 0);
+        assert_eq!(file.node_count().unwrap(), 1);
+    }
+    {
+        let mut file = SgxFile::create("sgx_file_len").unwrap();
+        file.write_all(&[0x33; 3072]).unwrap();
+        assert_eq!(file.logical_len().unwrap(), 3072);
+        assert_eq!(file.node_count().unwrap(), 1);
+        file.write_all(&[0x33; 1]).unwrap();
+        assert_eq!(file.node_count().unwrap(), 3);
+    }
+    {
+        let mut file = SgxFile::open("sgx_file_len").unwrap();
+        file.seek(SeekFrom::Start(7)).unwrap();
+        assert_eq!(file.logical_len().unwrap(), 3073);
+        assert_eq!(file.seek(SeekFrom::Current(0)).unwrap(), 7);
+    }
+    let host_len = File::open("sgx_file_len").unwrap().metadata().unwrap().len();
+    assert_eq!(host_len, 3 * 4096);
+    assert!(sgxfs::remove("sgx_file_len").is_ok());
+}
+
+pub fn test_sgxfs_drop_hook() {
+    use std::io::{self, Seek, SeekFrom};
+    use std::sgxfs::OpenOptions;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::untrusted::fs::OpenOptions as UntrustedOpenOptions;
+    use std::vec::Vec;
+
+    static DROP_ERRORS: AtomicUsize = AtomicUsize::new(0);
+    fn hook(_: &io::Error) {
+        DROP_ERRORS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .on_drop_error(hook)
+            .open("sgx_file_drop_hook")
+            .unwrap();
+        file.write_all(b"not flushed explicitly").unwrap();
+    }
+    assert_eq!(DROP_ERRORS.load(Ordering::SeqCst), 0);
+
+    let mut s = String::new();
+    SgxFile::open("sgx_file_drop_hook")
+        .unwrap()
+        .read_to_string(&mut s)
+        .unwrap();
+    assert_eq!(s, "not flushed explicitly");
+
+    // Reading a tampered node marks the file as corrupted, so the flush on
+    // drop fails and the hook is called.
+    {
+        let mut file = SgxFile::create("sgx_file_drop_hook").unwrap();
+        file.write_all(&[0x5a_u8; 16 * 1024]).unwrap();
+    }
+    {
+        let mut file = UntrustedOpenOptions::new().write(true).open("sgx_file_drop_hook").unwrap();
+        file.seek(SeekFrom::Start(3 * 4096 + 100)).unwrap();
+        file.write_all(&[0xff; 16]).unwrap();
+    }
+    {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .update(true)
+            .on_drop_error(hook)
+            .open("sgx_file_drop_hook")
+            .unwrap();
+        let mut buf = Vec::new();
+        assert!(file.read_to_end(&mut buf).is_err());
+        assert_eq!(DROP_ERRORS.load(Ordering::SeqCst), 0);
+    }
+    assert_eq!(DROP_ERRORS.load(Ordering::SeqCst), 1);
+    assert!(sgxfs::remove("sgx_file_drop_hook").is_ok());
+}
+
+pub fn test_sgxfs_write_through() {
+    use std::sgxfs::OpenOptions;
+    use std::vec::Vec;
+
+    {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .write_through(true)
+            .open("sgx_file_write_through")
+            .unwrap();
+        file.write_all(b"durable").unwrap();
+        // Nothing has been flushed or closed, the node is already on disk.
+        let host_len = File::open("sgx_file_write_through").unwrap().metadata().unwrap().len();
+        assert_eq!(host_len, 4096);
+
+        file.set_write_through(false);
+        file.write_all(&[0x77; 4096]).unwrap();
+        let host_len = File::open("sgx_file_write_through").unwrap().metadata().unwrap().len();
+        assert_eq!(host_len, 4096);
+    }
+
+    let mut data = Vec::new();
+    SgxFile::open("sgx_file_write_through")
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+    assert_eq!(&data[..7], b"durable");
+    assert_eq!(data.len(), 7 + 4096);
+    assert!(sgxfs::remove("sgx_file_write_through").is_ok());
+}
+
 pub fn test_fs() {
     {
         let f = File::create("foo.txt");
@@ -0,0 +1,225 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use sgx_tcrypto::*;
+use sgx_types::*;
+use std::vec::Vec;
+use utils::*;
+
+use core::mem;
+use core::slice;
+
+const PFS_KEY: sgx_key_128bit_t = [
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+];
+const PFS_NODE_NUMBER: u64 = 7;
+
+// Computed independently with an AES-128-CMAC over the 112-byte kdf_input_t layout:
+// key 000102..0f, node number 7, nonce 202122..3f.
+const PFS_KEY_TRUTH: [(&[u8], &str); 3] = [
+    (SGX_PFS_METADATA_KEY_LABEL, "2f21c3997c950f2aef505a519ab4949e"),
+    (SGX_PFS_MASTER_KEY_LABEL, "06d5bc0719c555741a4e97524b6c43c1"),
+    (SGX_PFS_RANDOM_KEY_LABEL, "3ae10fbf308daeb331b7be2db85ab04f"),
+];
+const PFS_KEY256_TRUTH: &str = "0ef5ee708c30fa41e77746358b4f1f5d50b8ef205875e9521da42145754306e2";
+const PFS_DATA_KEY_TRUTH: &str = "7ec6411bd5766f8e632aab7bb10ce0a6";
+const PFS_DOMAIN_KEY_TRUTH: [(&[u8], &str); 2] = [
+    (b"tenant-a", "158210f1ec0bc868031403253fd73bda"),
+    (b"tenant-b", "4d484cc01d6616e18aac85966abf2873"),
+];
+
+fn pfs_nonce() -> sgx_key_id_t {
+    let mut nonce = sgx_key_id_t::default();
+    for (i, b) in nonce.id.iter_mut().enumerate() {
+        *b = 0x20 + i as u8;
+    }
+    nonce
+}
+
+pub fn test_rsgx_key128_ct_eq() {
+    let key = [0x5a_u8; 16];
+    assert!(rsgx_key128_ct_eq(&key, &key));
+    assert!(rsgx_key128_ct_eq(&[0; 16], &[0; 16]));
+
+    // One flipped bit, in every byte position and at both ends of the byte.
+    for i in 0..16 {
+        for bit in [0x01_u8, 0x80].iter() {
+            let mut other = key;
+            other[i] ^= bit;
+            assert!(!rsgx_key128_ct_eq(&key, &other));
+            assert!(!rsgx_key128_ct_eq(&other, &key));
+        }
+    }
+
+    let mut other = [0_u8; 16];
+    for (i, b) in other.iter_mut().enumerate() {
+        *b = !key[i];
+    }
+    assert!(!rsgx_key128_ct_eq(&key, &other));
+}
+
+pub fn test_rsgx_pfs_derive_key() {
+    let nonce = pfs_nonce();
+    for (label, truth) in PFS_KEY_TRUTH.iter() {
+        let key = rsgx_pfs_derive_key(&PFS_KEY, label, PFS_NODE_NUMBER, &nonce).unwrap();
+        assert_eq!(hex_to_bytes(truth), key);
+    }
+
+    let key256 =
+        rsgx_pfs_derive_key256(&PFS_KEY, SGX_PFS_MASTER_KEY_LABEL, PFS_NODE_NUMBER, &nonce).unwrap();
+    assert_eq!(hex_to_bytes(PFS_KEY256_TRUTH), &key256[..]);
+    // output_len is part of the input, so the first block differs from the 128-bit key.
+    assert_ne!(hex_to_bytes(PFS_KEY_TRUTH[1].1), &key256[..16]);
+
+    // Every input changes the key.
+    let key = rsgx_pfs_derive_key(&PFS_KEY, SGX_PFS_MASTER_KEY_LABEL, PFS_NODE_NUMBER, &nonce).unwrap();
+    let mut other_nonce = nonce;
+    other_nonce.id[31] ^= 1;
+    let mut other_key = PFS_KEY;
+    other_key[0] ^= 1;
+    assert_ne!(key, rsgx_pfs_derive_key(&PFS_KEY, SGX_PFS_MASTER_KEY_LABEL, 8, &nonce).unwrap());
+    assert_ne!(key, rsgx_pfs_derive_key(&PFS_KEY, SGX_PFS_MASTER_KEY_LABEL, PFS_NODE_NUMBER, &other_nonce).unwrap());
+    assert_ne!(key, rsgx_pfs_derive_key(&other_key, SGX_PFS_MASTER_KEY_LABEL, PFS_NODE_NUMBER, &nonce).unwrap());
+
+    let label = [b'L'; 64];
+    assert!(rsgx_pfs_derive_key(&PFS_KEY, &label, 0, &nonce).is_ok());
+    let long_label: Vec<u8> = vec![b'L'; 65];
+    assert_eq!(
+        rsgx_pfs_derive_key(&PFS_KEY, &long_label, 0, &nonce),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    );
+    assert_eq!(
+        rsgx_pfs_derive_key256(&PFS_KEY, &long_label, 0, &nonce),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    );
+    assert_eq!(
+        rsgx_pfs_derive_key(&PFS_KEY, &[], 0, &nonce),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    );
+}
+
+pub fn test_rsgx_pfs_data_key() {
+    let nonce = pfs_nonce();
+    let data_key =
+        rsgx_pfs_derive_key(&PFS_KEY, SGX_PFS_DATA_KEY_LABEL, PFS_NODE_NUMBER, &nonce).unwrap();
+    assert_eq!(hex_to_bytes(PFS_DATA_KEY_TRUTH), data_key);
+    for (label, _) in PFS_KEY_TRUTH.iter() {
+        let key = rsgx_pfs_derive_key(&PFS_KEY, label, PFS_NODE_NUMBER, &nonce).unwrap();
+        assert_ne!(key, data_key);
+    }
+}
+
+pub fn test_rsgx_pfs_derive_keys_random() {
+    let node_numbers = [0_u64, 1, 0x1_0000_0000];
+    let mut keys = [sgx_key_128bit_t::default(); 3];
+    let mut nonces = [sgx_key_id_t::default(); 3];
+    rsgx_pfs_derive_keys_random(&PFS_KEY, SGX_PFS_RANDOM_KEY_LABEL, &node_numbers, &mut keys, &mut nonces)
+        .unwrap();
+    for i in 0..node_numbers.len() {
+        let key = rsgx_pfs_derive_key(&PFS_KEY, SGX_PFS_RANDOM_KEY_LABEL, node_numbers[i], &nonces[i]).unwrap();
+        assert_eq!(key, keys[i]);
+    }
+    assert_ne!(nonces[0].id, nonces[1].id);
+    assert_ne!(nonces[1].id, nonces[2].id);
+
+    assert_eq!(
+        rsgx_pfs_derive_keys_random(&PFS_KEY, SGX_PFS_RANDOM_KEY_LABEL, &node_numbers, &mut keys[..2], &mut nonces),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    );
+    assert_eq!(
+        rsgx_pfs_derive_keys_random(&PFS_KEY, SGX_PFS_RANDOM_KEY_LABEL, &node_numbers, &mut keys, &mut nonces[..2]),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    );
+    assert!(rsgx_pfs_derive_keys_random(&PFS_KEY, SGX_PFS_RANDOM_KEY_LABEL, &[], &mut [], &mut []).is_ok());
+
+    // A bad label fails after the nonces are drawn, and no key is left behind.
+    let long_label: Vec<u8> = vec![b'L'; 65];
+    assert_eq!(
+        rsgx_pfs_derive_keys_random(&PFS_KEY, &long_label, &node_numbers, &mut keys, &mut nonces),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    );
+    assert!(keys.iter().all(|k| k.iter().all(|b| *b == 0)));
+}
+
+pub fn test_rsgx_pfs_derive_key_random() {
+    let (key, nonce) = rsgx_pfs_derive_key_random(&PFS_KEY, SGX_PFS_MASTER_KEY_LABEL, 0).unwrap();
+    assert_eq!(key, rsgx_pfs_derive_key(&PFS_KEY, SGX_PFS_MASTER_KEY_LABEL, 0, &nonce).unwrap());
+
+    let (other_key, other_nonce) = rsgx_pfs_derive_key_random(&PFS_KEY, SGX_PFS_MASTER_KEY_LABEL, 0).unwrap();
+    assert_ne!(nonce.id, other_nonce.id);
+    assert_ne!(key, other_key);
+
+    let long_label: Vec<u8> = vec![b'L'; 65];
+    assert_eq!(
+        rsgx_pfs_derive_key_random(&PFS_KEY, &long_label, 0).map(|(k, _)| k),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    );
+}
+
+pub fn test_sgx_pfs_kdf_input_layout() {
+    assert_eq!(mem::size_of::<sgx_pfs_kdf_input_t>(), 112);
+
+    let mut input = sgx_pfs_kdf_input_t::default();
+    input.index = 1;
+    input.label[..SGX_PFS_METADATA_KEY_LABEL.len()].copy_from_slice(SGX_PFS_METADATA_KEY_LABEL);
+    input.node_number = PFS_NODE_NUMBER;
+    input.nonce = pfs_nonce();
+    input.output_len = 0x80;
+
+    let bytes = unsafe {
+        slice::from_raw_parts(
+            &input as *const sgx_pfs_kdf_input_t as *const u8,
+            mem::size_of::<sgx_pfs_kdf_input_t>(),
+        )
+    };
+    assert_eq!(&bytes[0..4], &[1, 0, 0, 0]);
+    assert_eq!(&bytes[4..4 + SGX_PFS_METADATA_KEY_LABEL.len()], SGX_PFS_METADATA_KEY_LABEL);
+    assert!(bytes[4 + SGX_PFS_METADATA_KEY_LABEL.len()..68].iter().all(|b| *b == 0));
+    assert_eq!(&bytes[68..76], &PFS_NODE_NUMBER.to_le_bytes());
+    assert_eq!(&bytes[76..108], &pfs_nonce().id[..]);
+    assert_eq!(&bytes[108..112], &[0x80, 0, 0, 0]);
+
+    // A CMAC over the structure is the key the protected file system derives.
+    let key = rsgx_rijndael128_cmac_msg(&PFS_KEY, &input).unwrap();
+    assert_eq!(hex_to_bytes(PFS_KEY_TRUTH[0].1), key);
+}
+
+pub fn test_rsgx_pfs_derive_key_in_domain() {
+    let nonce = pfs_nonce();
+    for (domain, truth) in PFS_DOMAIN_KEY_TRUTH.iter() {
+        let key = rsgx_pfs_derive_key_in_domain(&PFS_KEY, SGX_PFS_DATA_KEY_LABEL, domain, PFS_NODE_NUMBER, &nonce)
+            .unwrap();
+        assert_eq!(hex_to_bytes(truth), key);
+        assert_ne!(hex_to_bytes(PFS_DATA_KEY_TRUTH), key);
+    }
+
+    let label = [b'L'; 62];
+    assert!(rsgx_pfs_derive_key_in_domain(&PFS_KEY, &label, b"d", 0, &nonce).is_ok());
+    for (label, domain) in [
+        (&label[..], &b"dd"[..]),
+        (SGX_PFS_DATA_KEY_LABEL, &b""[..]),
+        (SGX_PFS_DATA_KEY_LABEL, &b"a\0"[..]),
+        (&b""[..], &b"d"[..]),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            rsgx_pfs_derive_key_in_domain(&PFS_KEY, label, domain, 0, &nonce),
+            Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        );
+    }
+}
@@ -36,6 +36,28 @@ pub fn test_rand_os_sgxrng() {
     assert_ne!(cmp, true);
 }
 
+pub fn test_rand_os_fill_contiguous() {
+    let mut os_rng = os::SgxRng::new().unwrap();
+
+    let mut key_id = sgx_types::sgx_key_id_t::default();
+    os_rng.fill_contiguous(&mut key_id).unwrap();
+    assert!(key_id.id.iter().any(|b| *b != 0));
+    // The last bytes must be covered too.
+    assert!(key_id.id[24..].iter().any(|b| *b != 0));
+
+    let mut key = sgx_types::sgx_key_128bit_t::default();
+    os_rng.fill_contiguous(&mut key).unwrap();
+    assert!(key.iter().any(|b| *b != 0));
+
+    let mut cpu_svn = sgx_types::sgx_cpu_svn_t::default();
+    os_rng.fill_contiguous(&mut cpu_svn).unwrap();
+    assert!(cpu_svn.svn.iter().any(|b| *b != 0));
+
+    let mut buf = [0_u8; 64];
+    os_rng.try_fill_bytes(&mut buf).unwrap();
+    assert!(buf.iter().any(|b| *b != 0));
+}
+
 // pub mod distribution
 // Too hard to test
 pub fn test_rand_distributions() {
@@ -792,5 +814,105 @@ pub fn test_rand_reseeding() {
     assert!(sum / v.len() as f64 != 0.0);
 }
 
+pub fn test_rand_gen_range_unbiased() {
+    const SAMPLES: usize = 60000;
+    let mut rng = thread_rng();
+
+    // With plain `% 192` on a u8, 0..64 would come up half of the time
+    // instead of a third.
+    let low = (0..SAMPLES)
+        .filter(|_| rng.gen_range(0_u8, 192) < 64)
+        .count();
+    assert!(low > 19000 && low < 21000, "biased gen_range: {}", low);
+
+    let mut counts = [0_usize; 6];
+    for _ in 0..SAMPLES {
+        let mut v = [0_u8, 1, 2];
+        rng.shuffle(&mut v);
+        let idx = match v {
+            [0, 1, 2] => 0,
+            [0, 2, 1] => 1,
+            [1, 0, 2] => 2,
+            [1, 2, 0] => 3,
+            [2, 0, 1] => 4,
+            _ => 5,
+        };
+        counts[idx] += 1;
+    }
+    for c in counts.iter() {
+        assert!(*c > 9300 && *c < 10700, "biased shuffle: {:?}", counts);
+    }
+}
+
+pub fn test_rand_callback_rng() {
+    use sgx_rand::callback::CallbackRng;
+
+    let mut counter = 0_u8;
+    let mut calls = 0;
+    {
+        let mut rng = CallbackRng::new(|buf: &mut [u8]| {
+            calls += 1;
+            for b in buf.iter_mut() {
+                *b = counter;
+                counter = counter.wrapping_add(1);
+            }
+        });
+        assert_eq!(rng.next_u32(), 0x0302_0100);
+        assert_eq!(rng.next_u64(), 0x0b0a_0908_0706_0504);
+        let mut v = [0_u8; 3];
+        rng.fill_bytes(&mut v);
+        assert_eq!(v, [12, 13, 14]);
+        rng.fill_bytes(&mut []);
+    }
+    assert_eq!(calls, 3);
+
+    let mut a = CallbackRng::new(|buf: &mut [u8]| buf.iter_mut().for_each(|b| *b = 0x42));
+    let mut b = CallbackRng::new(|buf: &mut [u8]| buf.iter_mut().for_each(|b| *b = 0x42));
+    let x: [u32; 8] = a.gen();
+    let y: [u32; 8] = b.gen();
+    assert_eq!(x, y);
+}
+
+pub fn test_rand_secure_random_bytes() {
+    let mut a = [0_u8; 64];
+    let mut b = [0_u8; 64];
+    assert!(secure_random_bytes(&mut a).is_ok());
+    assert!(secure_random_bytes(&mut b).is_ok());
+    assert!(a.iter().any(|x| *x != 0));
+    assert!(a[..] != b[..]);
+    assert!(secure_random_bytes(&mut []).is_ok());
+}
+
+pub fn test_rand_chacha_sgx_reseeding() {
+    use sgx_rand::reseeding::{ReseedWithSgxRng, ReseedingRng};
+
+    let seed: &[u32] = &[1, 2, 3, 4, 5, 6, 7, 8];
+    let mut ra: ChaChaRng = SeedableRng::from_seed(seed);
+    let mut rb: ChaChaRng = SeedableRng::from_seed(seed);
+    for _ in 0..64 {
+        assert_eq!(ra.next_u64(), rb.next_u64());
+    }
+
+    let plain: ChaChaRng = SeedableRng::from_seed(seed);
+    let mut reference = plain.clone();
+    let mut rs = ReseedingRng::new(plain, 64, ReseedWithSgxRng::new());
+    for _ in 0..16 {
+        assert_eq!(rs.next_u32(), reference.next_u32());
+    }
+    // The threshold is crossed here, so the key now comes from RDRAND.
+    let reseeded: [u32; 16] = rs.gen();
+    let expected: [u32; 16] = reference.gen();
+    assert!(reseeded != expected);
+    assert!(rs.reseeder.last_error().is_none());
+
+    // try_reseed reports the outcome and only replaces the state on success.
+    let mut rsdr = ReseedWithSgxRng::new();
+    let mut direct: ChaChaRng = SeedableRng::from_seed(seed);
+    assert!(rsdr.try_reseed(&mut direct).is_ok());
+    assert!(rsdr.last_error().is_none());
+    let mut unseeded: ChaChaRng = SeedableRng::from_seed(seed);
+    assert!(direct.next_u64() != unseeded.next_u64());
+}
+
 // No need for testing others
 // Already included in the above tests
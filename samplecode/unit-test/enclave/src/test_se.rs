@@ -0,0 +1,249 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use sgx_tse::*;
+use sgx_types::*;
+
+pub fn test_key_request_builder() {
+    let report = rsgx_self_report();
+    let mut key_id = sgx_key_id_t::default();
+    key_id.id[0] = 1;
+
+    let key_request = SgxKeyRequestBuilder::new(SGX_KEYSELECT_SEAL)
+        .key_id(key_id)
+        .build()
+        .unwrap();
+    assert_eq!(key_request.key_name, SGX_KEYSELECT_SEAL);
+    assert!(key_request.key_policy & SGX_KEYPOLICY_MRSIGNER != 0);
+    assert_eq!(key_request.isv_svn, report.body.isv_svn);
+    assert_eq!(key_request.cpu_svn.svn, report.body.cpu_svn.svn);
+    assert_eq!(key_request.config_svn, report.body.config_svn);
+    assert_eq!(key_request.attribute_mask.flags, TSEAL_DEFAULT_FLAGSMASK);
+    assert_eq!(key_request.misc_mask, TSEAL_DEFAULT_MISCMASK);
+    assert_eq!(key_request.key_id.id, key_id.id);
+    assert!(rsgx_get_key(&key_request).is_ok());
+
+    let key_request = SgxKeyRequestBuilder::new(SGX_KEYSELECT_SEAL)
+        .key_policy(SGX_KEYPOLICY_MRENCLAVE)
+        .misc_mask(0)
+        .build()
+        .unwrap();
+    assert_eq!(key_request.key_policy, SGX_KEYPOLICY_MRENCLAVE);
+    assert_eq!(key_request.misc_mask, 0);
+
+    assert!(SgxKeyRequestBuilder::new(SGX_KEYSELECT_SEAL)
+        .key_policy(SGX_KEYPOLICY_NOISVPRODID)
+        .build()
+        .is_err());
+    assert!(SgxKeyRequestBuilder::new(SGX_KEYSELECT_SEAL)
+        .key_policy(0x8000 | SGX_KEYPOLICY_MRSIGNER)
+        .build()
+        .is_err());
+    assert!(SgxKeyRequestBuilder::new(SGX_KEYSELECT_SEAL + 1)
+        .build()
+        .is_err());
+
+    let key_request = SgxKeyRequestBuilder::new(SGX_KEYSELECT_SEAL)
+        .expected_mr_signer(report.body.mr_signer)
+        .expected_mr_enclave(report.body.mr_enclave)
+        .build();
+    assert!(key_request.is_ok());
+    let mut other = report.body.mr_signer;
+    other.m[0] ^= 1;
+    assert_eq!(
+        SgxKeyRequestBuilder::new(SGX_KEYSELECT_SEAL)
+            .expected_mr_signer(other)
+            .build()
+            .err(),
+        Some(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    );
+    let mut other = report.body.mr_enclave;
+    other.m[31] ^= 1;
+    assert_eq!(
+        SgxKeyRequestBuilder::new(SGX_KEYSELECT_SEAL)
+            .expected_mr_signer(report.body.mr_signer)
+            .expected_mr_enclave(other)
+            .build()
+            .err(),
+        Some(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    );
+
+    let kss_request = SgxKeyRequestBuilder::new(SGX_KEYSELECT_SEAL)
+        .key_policy(SGX_KEYPOLICY_MRSIGNER | SGX_KEYPOLICY_CONFIGID)
+        .build();
+    if report.body.attributes.flags & SGX_FLAGS_KSS != 0 {
+        assert_eq!(
+            kss_request.unwrap().key_policy,
+            SGX_KEYPOLICY_MRSIGNER | SGX_KEYPOLICY_CONFIGID
+        );
+    } else {
+        assert!(kss_request.is_err());
+    }
+}
+
+pub fn test_key_request_builder_key_names() {
+    let report = rsgx_self_report();
+
+    let key_request = SgxKeyRequestBuilder::seal().build().unwrap();
+    assert_eq!(key_request.key_name, SGX_KEYSELECT_SEAL);
+    let key_request = SgxKeyRequestBuilder::report().build().unwrap();
+    assert_eq!(key_request.key_name, SGX_KEYSELECT_REPORT);
+    assert!(rsgx_get_key(&key_request).is_ok());
+
+    for builder in &[
+        SgxKeyRequestBuilder::provision(),
+        SgxKeyRequestBuilder::provision_seal(),
+    ] {
+        assert_eq!(
+            builder
+                .key_policy(SGX_KEYPOLICY_MRENCLAVE | SGX_KEYPOLICY_MRSIGNER)
+                .build()
+                .err(),
+            Some(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        );
+        let mut other = report.body.mr_signer;
+        other.m[0] ^= 1;
+        assert_eq!(
+            builder.expected_mr_signer(other).build().err(),
+            Some(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+        );
+        let key_request = builder.build();
+        if report.body.attributes.flags & SGX_FLAGS_PROVISION_KEY != 0 {
+            assert_eq!(key_request.unwrap().key_policy, SGX_KEYPOLICY_MRSIGNER);
+        } else {
+            assert_eq!(
+                key_request.err(),
+                Some(sgx_status_t::SGX_ERROR_INVALID_ATTRIBUTE)
+            );
+        }
+    }
+}
+
+pub fn test_key_request_layout() {
+    let mut key_request = sgx_key_request_t::default();
+    key_request.key_name = 0x0102;
+    key_request.key_policy = 0x0304;
+    key_request.isv_svn = 0x0506;
+    key_request.cpu_svn.svn = [0x10; SGX_CPUSVN_SIZE];
+    key_request.attribute_mask.flags = 0x1817_1615_1413_1211;
+    key_request.attribute_mask.xfrm = 0x2827_2625_2423_2221;
+    key_request.key_id.id = [0x30; SGX_KEYID_SIZE];
+    key_request.misc_mask = 0x4443_4241;
+    key_request.config_svn = 0x5251;
+
+    let bytes = unsafe {
+        std::slice::from_raw_parts(
+            &key_request as *const sgx_key_request_t as *const u8,
+            std::mem::size_of::<sgx_key_request_t>(),
+        )
+    };
+    assert_eq!(bytes.len(), 512);
+    assert_eq!(bytes[0..8], [0x02, 0x01, 0x04, 0x03, 0x06, 0x05, 0, 0]);
+    assert!(bytes[8..24].iter().all(|b| *b == 0x10));
+    assert_eq!(bytes[24..32], [0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18]);
+    assert_eq!(bytes[32..40], [0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27, 0x28]);
+    assert!(bytes[40..72].iter().all(|b| *b == 0x30));
+    assert_eq!(bytes[72..78], [0x41, 0x42, 0x43, 0x44, 0x51, 0x52]);
+    assert!(bytes[78..].iter().all(|b| *b == 0));
+}
+
+pub fn test_self_svn() {
+    let report = rsgx_self_report();
+    let (cpu_svn, isv_svn) = rsgx_self_svn();
+    assert_eq!(cpu_svn.svn, report.body.cpu_svn.svn);
+    assert_eq!(isv_svn, report.body.isv_svn);
+}
+
+pub fn test_self_report_ref() {
+    let report = rsgx_self_report();
+    let report_ref = rsgx_self_report_ref();
+    assert_eq!(report_ref.body.mr_enclave.m, report.body.mr_enclave.m);
+    assert_eq!(report_ref.mac, report.mac);
+    assert!(std::ptr::eq(report_ref, rsgx_self_report_ref()));
+
+    // A fresh EREPORT sees the same identity and SVNs as the cached report.
+    let target_info = rsgx_self_target().unwrap();
+    let fresh = rsgx_create_report(&target_info, &sgx_report_data_t::default()).unwrap();
+    assert_eq!(fresh.body.cpu_svn.svn, report_ref.body.cpu_svn.svn);
+    assert_eq!(fresh.body.misc_select, report_ref.body.misc_select);
+    assert_eq!(fresh.body.attributes.flags, report_ref.body.attributes.flags);
+    assert_eq!(fresh.body.attributes.xfrm, report_ref.body.attributes.xfrm);
+    assert_eq!(fresh.body.mr_enclave.m, report_ref.body.mr_enclave.m);
+    assert_eq!(fresh.body.mr_signer.m, report_ref.body.mr_signer.m);
+    assert_eq!(fresh.body.config_id[..], report_ref.body.config_id[..]);
+    assert_eq!(fresh.body.isv_prod_id, report_ref.body.isv_prod_id);
+    assert_eq!(fresh.body.isv_svn, report_ref.body.isv_svn);
+    assert_eq!(fresh.body.config_svn, report_ref.body.config_svn);
+}
+
+pub fn test_targeted_report() {
+    let target_info = rsgx_self_target().unwrap();
+    let report = rsgx_self_report_ref();
+    assert_eq!(target_info.mr_enclave.m, report.body.mr_enclave.m);
+    assert_eq!(target_info.misc_select, report.body.misc_select);
+
+    let mut report_data = sgx_report_data_t::default();
+    report_data.d[..32].copy_from_slice(&[0x42; 32]);
+    let mut report = rsgx_create_report(&target_info, &report_data).unwrap();
+    assert_eq!(report.body.report_data.d[..], report_data.d[..]);
+    assert!(rsgx_verify_report(&report).is_ok());
+
+    report.body.report_data.d[0] ^= 1;
+    assert_eq!(
+        rsgx_verify_report(&report),
+        Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    );
+}
+
+pub fn test_cpu_svn_is_at_least() {
+    use std::convert::TryFrom;
+
+    let low = sgx_cpu_svn_t::try_from(&[1_u8; SGX_CPUSVN_SIZE][..]).unwrap();
+    let mut high = low;
+    high.svn[3] = 2;
+    let mut mixed = low;
+    mixed.svn[0] = 0;
+    mixed.svn[15] = 5;
+
+    assert!(low.is_at_least(&low));
+    assert!(high.is_at_least(&low));
+    assert!(!low.is_at_least(&high));
+    assert!(!mixed.is_at_least(&low));
+    assert!(!low.is_at_least(&mixed));
+    assert_eq!(high.as_bytes()[3], 2);
+    assert!(sgx_cpu_svn_t::try_from(&[0_u8; 15][..]).is_err());
+
+    let (cpu_svn, _) = rsgx_self_svn();
+    assert!(cpu_svn.is_at_least(&sgx_cpu_svn_t::default()));
+}
+
+pub fn test_key_id_cpu_svn_conversions() {
+    let mut bytes = [0_u8; SGX_KEYID_SIZE];
+    bytes[0] = 0xa5;
+    bytes[31] = 0x5a;
+    let mut key_id = sgx_key_id_t::from(bytes);
+    assert_eq!(key_id.as_ref(), &bytes[..]);
+    key_id.as_mut()[1] = 7;
+    let back: [u8; SGX_KEYID_SIZE] = key_id.into();
+    assert_eq!(back[..2], [0xa5, 7]);
+    assert_eq!(back[31], 0x5a);
+
+    let (cpu_svn, _) = rsgx_self_svn();
+    let svn: [u8; SGX_CPUSVN_SIZE] = cpu_svn.into();
+    assert_eq!(sgx_cpu_svn_t::from(svn).as_ref(), cpu_svn.as_ref());
+    assert_eq!(&svn, cpu_svn.as_bytes());
+}
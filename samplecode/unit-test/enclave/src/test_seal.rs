@@ -113,3 +113,52 @@ pub fn test_mac_aadata_slice() {
     let inner_slice = unsafe { slice::from_raw_parts(inner as *mut u8, 10) };
     assert_eq!(inner_slice, aad_data);
 }
+
+pub fn test_unseal_with_aad() {
+    let data = [0x5a_u8; 32];
+    let aad = b"file v1";
+    let sealed_data = SgxSealedData::<[u8]>::seal_data_with_aad(aad, &data).unwrap();
+    assert_eq!(sealed_data.get_additional_txt()[..4], [7, 0, 0, 0]);
+    assert_eq!(&sealed_data.get_additional_txt()[4..], &aad[..]);
+
+    let unsealed_data = sealed_data.unseal_data_with_aad(aad).unwrap();
+    assert_eq!(&data[..], unsealed_data.get_decrypt_txt());
+
+    assert_eq!(
+        sealed_data.unseal_data_with_aad(b"file v2").err(),
+        Some(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    );
+    assert_eq!(
+        sealed_data.unseal_data_with_aad(b"file v").err(),
+        Some(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    );
+    assert_eq!(
+        sealed_data.unseal_data_with_aad(&[]).err(),
+        Some(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    );
+
+    // A plain seal_data blob has no length prefix.
+    let plain = SgxSealedData::<[u8]>::seal_data(aad, &data).unwrap();
+    assert_eq!(
+        plain.unseal_data_with_aad(aad).err(),
+        Some(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    );
+
+    let number = 0x1234_5678_u64;
+    let sealed_number = SgxSealedData::<u64>::seal_data_with_aad(&[], &number).unwrap();
+    let unsealed_number = sealed_number.unseal_data_with_aad(&[]).unwrap();
+    assert_eq!(*unsealed_number.get_decrypt_txt(), number);
+
+    let max_aad = vec![0xa5_u8; 64 * 1024];
+    let sealed_data = SgxSealedData::<[u8]>::seal_data_with_aad(&max_aad, &data).unwrap();
+    assert!(sealed_data.unseal_data_with_aad(&max_aad).is_ok());
+    let big_aad = vec![0xa5_u8; 64 * 1024 + 1];
+    assert_eq!(
+        SgxSealedData::<[u8]>::seal_data_with_aad(&big_aad, &data).err(),
+        Some(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    );
+    assert_eq!(
+        sealed_data.unseal_data_with_aad(&big_aad).err(),
+        Some(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    );
+}
@@ -1,7 +1,10 @@
 use sgx_types::metadata::*;
+use sgx_types::secret::*;
 use sgx_types::*;
 
+use core::convert::TryFrom;
 use core::mem;
+use core::ptr;
 
 pub fn check_metadata_size() {
     assert_eq!(mem::size_of::<layout_group_t>(), 32);
@@ -31,3 +34,44 @@ pub fn check_version() {
         MINOR_VERSION as u64
     );
 }
+
+pub fn check_key_types_try_from() {
+    let bytes = [0x5a_u8; 64];
+
+    assert!(sgx_cpu_svn_t::try_from(&bytes[..SGX_CPUSVN_SIZE - 1]).is_err());
+    assert!(sgx_cpu_svn_t::try_from(&bytes[..SGX_CPUSVN_SIZE + 1]).is_err());
+    let cpu_svn = sgx_cpu_svn_t::try_from(&bytes[..SGX_CPUSVN_SIZE]).unwrap();
+    assert_eq!(cpu_svn.svn, [0x5a_u8; SGX_CPUSVN_SIZE]);
+
+    assert!(sgx_key_id_t::try_from(&bytes[..SGX_KEYID_SIZE - 1]).is_err());
+    assert!(sgx_key_id_t::try_from(&bytes[..SGX_KEYID_SIZE + 1]).is_err());
+    let key_id = sgx_key_id_t::try_from(&bytes[..SGX_KEYID_SIZE]).unwrap();
+    assert_eq!(key_id.id, [0x5a_u8; SGX_KEYID_SIZE]);
+}
+
+pub fn check_secret_redacts_debug() {
+    #[derive(Debug)]
+    struct MasterKey {
+        key: Secret<[u8; 16]>,
+        count: u32,
+    }
+
+    let master = MasterKey {
+        key: Secret::new([0xab_u8; 16]),
+        count: 3,
+    };
+    let formatted = format!("{:?}", master);
+    assert_eq!(formatted, "MasterKey { key: Secret(****), count: 3 }");
+    assert!(!formatted.contains("171"));
+    assert_eq!(format!("{}", master.key), "****");
+    assert_eq!(master.key.expose(), &[0xab_u8; 16]);
+}
+
+pub fn check_secret_zeroed_on_drop() {
+    let mut slot = mem::MaybeUninit::new(SecretKey128::new([0xab_u8; 16]));
+    unsafe {
+        ptr::drop_in_place(slot.as_mut_ptr());
+        let bytes = ptr::read_volatile(slot.as_ptr() as *const [u8; 16]);
+        assert_eq!(bytes, [0_u8; 16]);
+    }
+}
@@ -0,0 +1,72 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! An RNG driven by a closure, for deterministic tests.
+
+use crate::Rng;
+
+/// An RNG that takes every byte it produces from a closure.
+///
+/// This is meant for fuzzing and for reproducing failures in code that
+/// consumes random bytes, such as nonce or key ID generation: the closure
+/// decides exactly what the code under test sees. It produces no entropy
+/// of its own, so it is only compiled with the `callback_rng` feature,
+/// which must never be enabled for a production enclave.
+///
+/// # Example
+///
+/// ```rust
+/// use sgx_rand::{callback, Rng};
+///
+/// let mut counter = 0_u8;
+/// let mut rng = callback::CallbackRng::new(|buf: &mut [u8]| {
+///     for b in buf.iter_mut() {
+///         *b = counter;
+///         counter = counter.wrapping_add(1);
+///     }
+/// });
+/// assert_eq!(rng.next_u32(), 0x0302_0100);
+/// ```
+pub struct CallbackRng<F> {
+    callback: F,
+}
+
+impl<F: FnMut(&mut [u8])> CallbackRng<F> {
+    /// Create a new `CallbackRng` that fills its output with `callback`.
+    pub fn new(callback: F) -> CallbackRng<F> {
+        CallbackRng { callback }
+    }
+}
+
+impl<F: FnMut(&mut [u8])> Rng for CallbackRng<F> {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0; 4];
+        (self.callback)(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0; 8];
+        (self.callback)(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+    fn fill_bytes(&mut self, v: &mut [u8]) {
+        if v.is_empty() {
+            return;
+        }
+        (self.callback)(v)
+    }
+}
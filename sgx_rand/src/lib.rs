@@ -57,6 +57,8 @@ pub mod reseeding;
 mod rand_impls;
 pub mod os;
 pub mod read;
+#[cfg(feature = "callback_rng")]
+pub mod callback;
 
 #[allow(bad_style)]
 type w64 = w<u64>;
@@ -791,6 +793,20 @@ pub fn random<T: Rand>() -> T {
     thread_rng().gen()
 }
 
+/// Fill `buf` with bytes taken straight from RDRAND.
+///
+/// This is the function to use for key and nonce material. Unlike
+/// `thread_rng` or `random`, nothing goes through a seeded userspace
+/// generator. If RDRAND still fails after `rsgx_read_rand` has used up
+/// its retries, the error is returned and `buf` must not be used. There
+/// is never a fallback to weaker entropy. An empty `buf` succeeds
+/// without touching RDRAND.
+pub fn secure_random_bytes(buf: &mut [u8]) -> sgx_types::SgxError {
+    SgxRng::new()
+        .map_err(|_| sgx_types::sgx_status_t::SGX_ERROR_UNEXPECTED)?
+        .try_fill_bytes(buf)
+}
+
 /// Randomly sample up to `amount` elements from a finite iterator.
 /// The order of elements in the sample is not random.
 ///
@@ -19,9 +19,20 @@
 //! generators.
 
 use std::{io, mem, fmt};
+use sgx_types::SgxError;
 use crate::Rng;
 
 /// A random number generator
+///
+/// Random bytes come from `sgx_read_rand`, which executes RDRAND inside the
+/// enclave. Each 32-bit RDRAND read is retried up to 10 times before the
+/// call fails with `SGX_ERROR_UNEXPECTED`. A transient shortage of entropy
+/// is therefore absorbed inside `sgx_read_rand`, and an error returned to
+/// the caller means the hardware generator is not delivering. Retrying at
+/// the caller is unlikely to help.
+///
+/// `SgxRng` holds no state, so creating one is free and there is nothing
+/// to cache between calls.
 pub struct SgxRng(imp::SgxRng);
 
 impl SgxRng {
@@ -29,6 +40,19 @@ impl SgxRng {
     pub fn new() -> io::Result<SgxRng> {
         imp::SgxRng::new().map(SgxRng)
     }
+
+    /// Fill `dest` with random data, returning an error instead of
+    /// panicking like `fill_bytes` does.
+    pub fn try_fill_bytes(&mut self, dest: &mut [u8]) -> SgxError {
+        self.0.try_fill_bytes(dest)
+    }
+
+    /// Fill every byte of `value` with random data, e.g. an `sgx_key_id_t`,
+    /// an `sgx_cpu_svn_t` or an `sgx_key_128bit_t`. The whole object is
+    /// always filled.
+    pub fn fill_contiguous<T: AsMut<[u8]> + ?Sized>(&mut self, value: &mut T) -> SgxError {
+        self.try_fill_bytes(value.as_mut())
+    }
 }
 
 impl Rng for SgxRng {
@@ -82,6 +106,14 @@ mod imp {
         pub fn new() -> io::Result<SgxRng> {
             Ok(SgxRng)
         }
+
+        pub fn try_fill_bytes(&mut self, v: &mut [u8]) -> SgxError {
+            // sgx_read_rand rejects a zero length.
+            if v.is_empty() {
+                return Ok(());
+            }
+            getrandom(v)
+        }
     }
 
     impl Rng for SgxRng {
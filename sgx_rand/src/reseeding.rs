@@ -20,7 +20,9 @@
 
 use std::default::Default;
 
-use crate::{Rng, SeedableRng};
+use sgx_types::{sgx_status_t, SgxError, SgxResult};
+
+use crate::{Rand, Rng, SeedableRng, SgxRng};
 
 /// How many bytes of entropy the underling RNG is allowed to generate
 /// before it is reseeded
@@ -151,3 +153,116 @@ impl<R: Rng + Default> Reseeder<R> for ReseedWithDefault {
 impl Default for ReseedWithDefault {
     fn default() -> ReseedWithDefault { ReseedWithDefault }
 }
+
+/// Reseed an RNG with fresh output of `SgxRng`, i.e. RDRAND inside the enclave.
+///
+/// The RNG is replaced by a new instance created with `Rand`, so its whole
+/// state is rekeyed. Wrapping a `ChaChaRng` in a `ReseedingRng` with this
+/// reseeder gives a ChaCha20 DRBG that calls RDRAND once per
+/// `generation_threshold` bytes instead of once per request. Because the
+/// key is replaced, a leaked state only exposes output produced since the
+/// last reseed.
+///
+/// A failing `sgx_read_rand` never aborts the enclave. `Reseeder::reseed`
+/// cannot return an error, so it keeps the RNG on its previous state and
+/// records the status, which `last_error` reports until a later reseed
+/// succeeds. Callers that must not continue on the old state call
+/// `try_reseed` and handle the error themselves.
+///
+/// # Example
+///
+/// ```rust
+/// use sgx_rand::{ChaChaRng, Rng, SgxRng};
+/// use sgx_rand::reseeding::{ReseedWithSgxRng, ReseedingRng};
+///
+/// let inner: ChaChaRng = SgxRng::new().unwrap().gen();
+/// let mut rng = ReseedingRng::new(inner, 1024 * 1024, ReseedWithSgxRng::new());
+/// let mut nonce = [0_u8; 32];
+/// rng.fill_bytes(&mut nonce);
+/// assert!(rng.reseeder.last_error().is_none());
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReseedWithSgxRng {
+    error: Option<sgx_status_t>,
+}
+
+impl ReseedWithSgxRng {
+    /// Create a reseeder with no recorded failure.
+    pub fn new() -> ReseedWithSgxRng {
+        ReseedWithSgxRng { error: None }
+    }
+
+    /// Replace `rng` with a new instance seeded from `SgxRng`.
+    ///
+    /// If `sgx_read_rand` fails, `rng` is left unchanged and the status is
+    /// returned (and kept for `last_error`).
+    pub fn try_reseed<R: Rand>(&mut self, rng: &mut R) -> SgxError {
+        let result = CheckedSgxRng::new().and_then(|mut source| {
+            let fresh = R::rand(&mut source);
+            match source.error {
+                Some(e) => Err(e),
+                None => {
+                    *rng = fresh;
+                    Ok(())
+                }
+            }
+        });
+        self.error = result.err();
+        result
+    }
+
+    /// The status of the last reseed if it failed, `None` after a
+    /// successful one.
+    pub fn last_error(&self) -> Option<sgx_status_t> {
+        self.error
+    }
+}
+
+impl<R: Rand> Reseeder<R> for ReseedWithSgxRng {
+    fn reseed(&mut self, rng: &mut R) {
+        // The error is kept in `self.error`; `rng` keeps its old state.
+        let _ = self.try_reseed(rng);
+    }
+}
+
+/// `SgxRng` that records the first `sgx_read_rand` failure instead of
+/// panicking. Everything it produces after a failure is zero, so whatever
+/// was built from it has to be discarded.
+struct CheckedSgxRng {
+    rng: SgxRng,
+    error: Option<sgx_status_t>,
+}
+
+impl CheckedSgxRng {
+    fn new() -> SgxResult<CheckedSgxRng> {
+        let rng = SgxRng::new().map_err(|_| sgx_status_t::SGX_ERROR_UNEXPECTED)?;
+        Ok(CheckedSgxRng { rng: rng, error: None })
+    }
+}
+
+impl Rng for CheckedSgxRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0_u8; 4];
+        self.fill_bytes(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0_u8; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if self.error.is_none() {
+            if let Err(e) = self.rng.try_fill_bytes(dest) {
+                self.error = Some(e);
+            }
+        }
+        if self.error.is_some() {
+            for b in dest.iter_mut() {
+                *b = 0;
+            }
+        }
+    }
+}
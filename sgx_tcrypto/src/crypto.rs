@@ -18,13 +18,23 @@
 //!
 //! Cryptographic Functions
 //!
+use crate::gf128::GHash;
 use core::cell::{Cell, RefCell};
+use core::cmp;
 use core::mem;
 use core::ops::{DerefMut, Drop};
 use core::ptr;
+use core::sync::atomic::{self, Ordering};
 use sgx_types::marker::ContiguousMemory;
 use sgx_types::*;
 
+pub(crate) fn zero_memory(buf: &mut [u8]) {
+    for b in buf.iter_mut() {
+        unsafe { ptr::write_volatile(b, 0) };
+    }
+    atomic::compiler_fence(Ordering::SeqCst);
+}
+
 ///
 /// The rsgx_sha256_msg function performs a standard SHA256 hash over the input data buffer.
 ///
@@ -548,6 +558,40 @@ impl SgxShaHandle {
         }
     }
 
+    ///
+    /// reset discards the data hashed so far and starts a new SHA256 hash.
+    ///
+    /// # Description
+    ///
+    /// This lets one handle compute many hashes in sequence, e.g. Init, Update, Final, reset, Update,
+    /// Final. The cryptography library has no call that reinitializes a SHA256 state in place, so reset
+    /// closes the current state and initializes a new one; the state is therefore set back to the
+    /// SHA256 IV, but it is reallocated by the library. reset may also be called on a handle that was
+    /// never initialized.
+    ///
+    /// # Requirements
+    ///
+    /// Library: libsgx_tcrypto.a
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The current state could not be closed.
+    ///
+    /// **SGX_ERROR_OUT_OF_MEMORY**
+    ///
+    /// Not enough memory is available to complete this operation.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// The SHA256 state is not initialized properly due to an internal cryptography library failure.
+    ///
+    pub fn reset(&self) -> SgxError {
+        self.close()?;
+        self.init()
+    }
+
     ///
     /// close cleans up and deallocates the SHA256 state that was allocated in function init.
     ///
@@ -1286,6 +1330,112 @@ where
     }
 }
 
+///
+/// The rsgx_rijndael128_cmac_verify_slice function computes the 128bit CMAC of the input data
+/// buffer and compares it with an expected tag in constant time.
+///
+/// # Description
+///
+/// Use this rather than comparing the output of rsgx_rijndael128_cmac_slice with `==`, which
+/// stops at the first differing byte and leaks how much of a forged tag was correct.
+///
+/// # Parameters
+///
+/// **key**
+///
+/// A pointer to key to be used in the CMAC hash operation. The size must be 128 bits.
+///
+/// **src**
+///
+/// A pointer to the input data stream to be hashed.
+///
+/// **mac**
+///
+/// The expected 128-bit CMAC tag.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a
+///
+/// # Return value
+///
+/// true if the computed tag matches mac, false otherwise.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The pointer is invalid.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// Not enough memory is available to complete this operation.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_rijndael128_cmac_verify_slice<T>(
+    key: &sgx_cmac_128bit_key_t,
+    src: &[T],
+    mac: &sgx_cmac_128bit_tag_t,
+) -> SgxResult<bool>
+where
+    T: Copy + ContiguousMemory,
+{
+    let computed = rsgx_rijndael128_cmac_slice(key, src)?;
+    Ok(rsgx_ct_compare(mac, &computed))
+}
+
+///
+/// The rsgx_rijndael128_cmac_subkeys function returns the CMAC subkeys K1 and K2 of a 128-bit
+/// AES key.
+///
+/// # Description
+///
+/// The subkeys are derived as in RFC 4493 section 2.3 (NIST SP 800-38B section 6.1): L is the
+/// encryption of the zero block, K1 is L doubled in GF(2^128) and K2 is K1 doubled. They are
+/// only needed to check intermediate values or for constructions built directly on the CMAC
+/// internals; rsgx_rijndael128_cmac_slice and SgxCmacHandle derive them internally.
+///
+/// # Parameters
+///
+/// **key**
+///
+/// A pointer to the 128-bit CMAC key.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a
+///
+/// # Return value
+///
+/// The pair (K1, K2). Both are secret and should be cleared after use.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The pointer is invalid.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// Not enough memory is available to complete this operation.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_rijndael128_cmac_subkeys(
+    key: &sgx_cmac_128bit_key_t,
+) -> SgxResult<(sgx_cmac_128bit_key_t, sgx_cmac_128bit_key_t)> {
+    let mut k1 = rsgx_aes_block_encrypt(key, &[0_u8; SGX_AESCTR_CTR_SIZE])?;
+    aes_siv_dbl(&mut k1);
+    let mut k2 = k1;
+    aes_siv_dbl(&mut k2);
+    Ok((k1, k2))
+}
+
 fn rsgx_cmac128_init(
     key: &sgx_cmac_128bit_key_t,
     cmac_handle: &mut sgx_cmac_state_handle_t,
@@ -1740,14 +1890,95 @@ where
     }
 }
 
-fn rsgx_hmac256_init(
-    key: &sgx_hmac_256bit_key_t,
-    hmac_handle: &mut sgx_hmac_state_handle_t,
-) -> sgx_status_t {
+///
+/// The rsgx_hmac_sha256_varkey_slice function performs a standard HMAC-SHA256 over the input
+/// data buffer with a key of any length.
+///
+/// # Description
+///
+/// This is the variable-length key counterpart of rsgx_hmac_sha256_slice. Keys longer than
+/// the 64-byte SHA-256 block size are hashed first and shorter keys are padded with zeros,
+/// as specified in [RFC 2104].
+///
+/// # Parameters
+///
+/// **key**
+///
+/// The HMAC key. It may be empty.
+///
+/// **src**
+///
+/// A pointer to the input data stream to be hashed.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a
+///
+/// # Return value
+///
+/// The 256-bit HMAC tag.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The input data is empty, or the key or input data is too large.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// Not enough memory is available to complete this operation.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_hmac_sha256_varkey_slice<T>(key: &[u8], src: &[T]) -> SgxResult<sgx_hmac_256bit_tag_t>
+where
+    T: Copy + ContiguousMemory,
+{
+    let size = mem::size_of_val(src);
+    if size == 0 {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    if size > i32::MAX as usize {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    if key.len() > i32::MAX as usize {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+
+    let zero_key = [0_u8; SGX_HMAC256_KEY_SIZE];
+    let key = if key.is_empty() { &zero_key[..] } else { key };
+
+    let mut mac = sgx_hmac_256bit_tag_t::default();
+    let ret = unsafe {
+        sgx_hmac_sha256_msg(
+            src.as_ptr() as *const u8,
+            size as i32,
+            key.as_ptr(),
+            key.len() as i32,
+            &mut mac as *mut sgx_hmac_256bit_tag_t as *mut u8,
+            SGX_HMAC256_MAC_SIZE as i32,
+        )
+    };
+    match ret {
+        sgx_status_t::SGX_SUCCESS => Ok(mac),
+        _ => Err(ret),
+    }
+}
+
+fn rsgx_hmac256_init(key: &[u8], hmac_handle: &mut sgx_hmac_state_handle_t) -> sgx_status_t {
+    // HMAC pads the key with zeros to the block size, so an empty key is
+    // equivalent to an all-zero one. The C library rejects zero-length keys.
+    let zero_key = [0_u8; SGX_HMAC256_KEY_SIZE];
+    let key = if key.is_empty() { &zero_key[..] } else { key };
+    if key.len() > i32::MAX as usize {
+        return sgx_status_t::SGX_ERROR_INVALID_PARAMETER;
+    }
     unsafe {
         sgx_hmac256_init(
-            key as *const sgx_hmac_256bit_key_t as *const u8,
-            SGX_HMAC256_KEY_SIZE as i32,
+            key.as_ptr(),
+            key.len() as i32,
             hmac_handle as *mut sgx_hmac_state_handle_t,
         )
     }
@@ -1818,6 +2049,12 @@ impl SgxHmacHandle {
     }
 
     pub fn init(&self, key: &sgx_hmac_256bit_key_t) -> SgxError {
+        self.init_slice(key)
+    }
+
+    /// Initializes the handle with a key of any length. Keys longer than the
+    /// SHA-256 block size are hashed first, as specified in RFC 2104.
+    pub fn init_slice(&self, key: &[u8]) -> SgxError {
         if self.initflag.get() {
             return Ok(());
         }
@@ -1925,6 +2162,321 @@ impl Drop for SgxHmacHandle {
     }
 }
 
+///
+/// The rsgx_hkdf_sha256_extract function performs the HKDF-Extract step with HMAC-SHA256.
+///
+/// # Description
+///
+/// This function implements the extract step of the HMAC-based Extract-and-Expand Key
+/// Derivation Function as specified in [RFC 5869]. The pseudorandom key is computed as
+/// HMAC-SHA256(salt, ikm). If the salt is empty, a string of 32 zero bytes is used instead.
+///
+/// # Parameters
+///
+/// **salt**
+///
+/// Optional salt value (a non-secret random value).
+///
+/// **ikm**
+///
+/// The input keying material.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a
+///
+/// # Return value
+///
+/// The 256-bit pseudorandom key.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The input keying material is empty, or the salt or input keying material is too large.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// Not enough memory is available to complete this operation.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_hkdf_sha256_extract(salt: &[u8], ikm: &[u8]) -> SgxResult<sgx_hmac_256bit_tag_t> {
+    // An empty salt is replaced by HashLen zero bytes, which is what
+    // rsgx_hmac_sha256_varkey_slice does for an empty key.
+    rsgx_hmac_sha256_varkey_slice(salt, ikm)
+}
+
+///
+/// The rsgx_hkdf_sha256_expand function performs the HKDF-Expand step with HMAC-SHA256.
+///
+/// # Description
+///
+/// This function implements the expand step of [RFC 5869]. It fills the output buffer with
+/// T(1) | T(2) | ..., where T(i) = HMAC-SHA256(prk, T(i-1) | info | i). At most 255 * 32
+/// bytes of output keying material can be produced from one pseudorandom key.
+///
+/// # Parameters
+///
+/// **prk**
+///
+/// The pseudorandom key, usually the output of rsgx_hkdf_sha256_extract.
+///
+/// **info**
+///
+/// Optional context and application specific information.
+///
+/// **okm**
+///
+/// The output keying material buffer. Its length selects the number of bytes to derive.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The output buffer is empty or longer than 255 * 32 bytes, or the info is too large.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// Not enough memory is available to complete this operation.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_hkdf_sha256_expand(
+    prk: &sgx_hmac_256bit_tag_t,
+    info: &[u8],
+    okm: &mut [u8],
+) -> SgxError {
+    let okm_len = okm.len();
+    if okm_len == 0 || okm_len > 255 * SGX_HMAC256_MAC_SIZE {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    if info.len() > i32::MAX as usize {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+
+    let handle = SgxHmacHandle::new();
+    let mut block = sgx_hmac_256bit_tag_t::default();
+    let result = okm
+        .chunks_mut(SGX_HMAC256_MAC_SIZE)
+        .enumerate()
+        .try_for_each(|(i, chunk)| {
+            handle.init(prk)?;
+            if i > 0 {
+                handle.update_slice(&block)?;
+            }
+            if !info.is_empty() {
+                handle.update_slice(info)?;
+            }
+            handle.update_msg(&((i + 1) as u8))?;
+            block = handle.get_hash()?;
+            handle.close()?;
+            chunk.copy_from_slice(&block[..chunk.len()]);
+            Ok(())
+        });
+    zero_memory(&mut block);
+    result
+}
+
+///
+/// The rsgx_kbkdf_cmac_counter function derives keying material with the counter-mode KDF of
+/// NIST SP 800-108, using AES-128 CMAC as the PRF.
+///
+/// # Description
+///
+/// The output buffer is filled with K(1) | K(2) | ..., where
+/// K(i) = CMAC(key, [i]_32 | label | 0x00 | context | [L]_32), the counter i starts at 1 and
+/// L is the output length in bits. Both integers are big-endian. This is the fixed input
+/// layout of SP 800-108 section 5.1 with the counter placed before the fixed data.
+///
+/// The key derivation of the protected file library is CMAC-based as well, but it encodes its
+/// own input structure and its keys can't be reproduced with this function.
+///
+/// # Parameters
+///
+/// **key**
+///
+/// The 128-bit key derivation key.
+///
+/// **label**
+///
+/// The purpose of the derived keying material. May be empty.
+///
+/// **context**
+///
+/// Information related to the derived keying material, such as the identities of the parties
+/// or a nonce. May be empty.
+///
+/// **dst**
+///
+/// The output keying material buffer. Its length selects the number of bytes to derive.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The output buffer is empty or its length in bits does not fit in 32 bits, or the label or
+/// context is too large.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// Not enough memory is available to complete this operation.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_kbkdf_cmac_counter(
+    key: &sgx_cmac_128bit_key_t,
+    label: &[u8],
+    context: &[u8],
+    dst: &mut [u8],
+) -> SgxError {
+    let dst_len = dst.len();
+    if dst_len == 0 || dst_len > (u32::MAX / 8) as usize {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    if label.len() > u32::MAX as usize || context.len() > u32::MAX as usize {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+
+    let l_bits = ((dst_len * 8) as u32).to_be_bytes();
+    let handle = SgxCmacHandle::new();
+    let mut block = sgx_cmac_128bit_tag_t::default();
+    let result = dst
+        .chunks_mut(SGX_CMAC_MAC_SIZE)
+        .enumerate()
+        .try_for_each(|(i, chunk)| {
+            handle.init(key)?;
+            handle.update_slice(&((i + 1) as u32).to_be_bytes())?;
+            if !label.is_empty() {
+                handle.update_slice(label)?;
+            }
+            handle.update_slice(&[0_u8])?;
+            if !context.is_empty() {
+                handle.update_slice(context)?;
+            }
+            handle.update_slice(&l_bits)?;
+            block = handle.get_hash()?;
+            handle.close()?;
+            chunk.copy_from_slice(&block[..chunk.len()]);
+            Ok(())
+        });
+    zero_memory(&mut block);
+    result
+}
+
+/// Minimum iteration count accepted by rsgx_pbkdf2_hmac_sha256, per [NIST SP 800-132].
+pub const SGX_PBKDF2_MIN_ITERATIONS: u32 = 1000;
+/// Minimum salt length accepted by rsgx_pbkdf2_hmac_sha256, per [NIST SP 800-132].
+pub const SGX_PBKDF2_MIN_SALT_SIZE: size_t = 16;
+
+///
+/// The rsgx_pbkdf2_hmac_sha256 function derives a 128-bit key from a password with PBKDF2.
+///
+/// # Description
+///
+/// This function implements PBKDF2 of [RFC 8018] with HMAC-SHA256 as the pseudorandom function
+/// and a 16-byte output, i.e. the first 16 bytes of T(1) = U(1) ^ U(2) ^ ... ^ U(c). It is meant
+/// for turning a passphrase into a user key for the protected file system or the seal APIs. The
+/// salt should be random and stored alongside the protected data.
+///
+/// # Parameters
+///
+/// **password**
+///
+/// The password or passphrase. It may be empty.
+///
+/// **salt**
+///
+/// The salt. It must be at least SGX_PBKDF2_MIN_SALT_SIZE bytes long.
+///
+/// **iterations**
+///
+/// The iteration count. It must be at least SGX_PBKDF2_MIN_ITERATIONS.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a
+///
+/// # Return value
+///
+/// The derived 128-bit key.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The salt is too short or too large, the iteration count is too low, or the password is too large.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// Not enough memory is available to complete this operation.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_pbkdf2_hmac_sha256(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+) -> SgxResult<sgx_key_128bit_t> {
+    if iterations < SGX_PBKDF2_MIN_ITERATIONS {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    if salt.len() < SGX_PBKDF2_MIN_SALT_SIZE || salt.len() > i32::MAX as usize {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    if password.len() > i32::MAX as usize {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+
+    let mut u = sgx_hmac_256bit_tag_t::default();
+    let mut t = sgx_hmac_256bit_tag_t::default();
+    let result = pbkdf2_hmac_sha256_block(password, salt, iterations, &mut u, &mut t).map(|_| {
+        let mut key = sgx_key_128bit_t::default();
+        key.copy_from_slice(&t[..mem::size_of::<sgx_key_128bit_t>()]);
+        key
+    });
+    zero_memory(&mut u);
+    zero_memory(&mut t);
+    result
+}
+
+fn pbkdf2_hmac_sha256_block(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    u: &mut sgx_hmac_256bit_tag_t,
+    t: &mut sgx_hmac_256bit_tag_t,
+) -> SgxError {
+    let handle = SgxHmacHandle::new();
+    handle.init_slice(password)?;
+    handle.update_slice(salt)?;
+    handle.update_slice(&1_u32.to_be_bytes())?;
+    *u = handle.get_hash()?;
+    handle.close()?;
+    *t = *u;
+    for _ in 1..iterations {
+        *u = rsgx_hmac_sha256_varkey_slice(password, &u[..])?;
+        t.iter_mut().zip(u.iter()).for_each(|(a, b)| *a ^= *b);
+    }
+    Ok(())
+}
+
 pub const SGX_AESCTR_CTR_SIZE: size_t = 16;
 pub type sgx_aes_ctr_128bit_ctr_t = [uint8_t; SGX_AESCTR_CTR_SIZE];
 
@@ -2025,44 +2577,280 @@ pub fn rsgx_aes_ctr_encrypt(
 }
 
 ///
-/// rsgx_aes_ctr_decrypt performs a Rijndael AES-CTR decryption operation.
-///
-/// Only a 128bit key size is supported by this Intel(R) SGX SDK cryptography library.
+/// rsgx_aes_ctr_decrypt performs a Rijndael AES-CTR decryption operation.
+///
+/// Only a 128bit key size is supported by this Intel(R) SGX SDK cryptography library.
+///
+/// # Description
+///
+/// This function decrypts the input data stream of a variable length according to
+/// the CTR mode as specified in [NIST SP 800-38A]. The counter can be thought
+/// of as an IV which increments on successive encryption or decryption calls. For
+/// a given dataset or data stream, the incremented counter block should be used
+/// on successive calls of the decryption process for that given stream. However,
+/// for new or different datasets/streams, the same counter should not be reused,
+/// instead initialize the counter for the new data set.
+///
+/// It is recommended that the source, destination and counter data buffers are
+/// allocated within the enclave.
+///
+/// # Parameters
+///
+/// **key**
+///
+/// A pointer to key to be used in the AES-CTR encryption operation. The size must be 128 bits.
+///
+/// **src**
+///
+/// A pointer to the input data stream to be decrypted.
+///
+/// **ctr**
+///
+/// A pointer to the initialization vector to be used in the AES-CTR calculation.
+///
+/// **ctr_inc_bits**
+///
+/// Specifies the number of bits in the counter to be incremented.
+///
+/// **dst**
+///
+/// A pointer to the output decrypted data buffer. This buffer should be allocated by the calling code.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The pointer is invalid.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// Not enough memory is available to complete this operation.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_aes_ctr_decrypt(
+    key: &sgx_aes_ctr_128bit_key_t,
+    src: &[u8],
+    ctr: &mut sgx_aes_ctr_128bit_ctr_t,
+    ctr_inc_bits: u32,
+    dst: &mut [u8],
+) -> SgxError {
+    let src_len = src.len();
+    if src_len > u32::MAX as usize {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    if src_len < 1 {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let dst_len = dst.len();
+    if dst_len > u32::MAX as usize {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    if dst_len < src_len {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+
+    let ret = unsafe {
+        sgx_aes_ctr_decrypt(
+            key as *const sgx_aes_ctr_128bit_key_t,
+            src.as_ptr(),
+            src.len() as u32,
+            ctr as *mut sgx_aes_ctr_128bit_ctr_t as *mut u8,
+            ctr_inc_bits,
+            dst.as_mut_ptr(),
+        )
+    };
+    match ret {
+        sgx_status_t::SGX_SUCCESS => Ok(()),
+        _ => Err(ret),
+    }
+}
+
+pub const SGX_AESSIV_KEY_SIZE: size_t = 32;
+pub const SGX_AESSIV_IV_SIZE: size_t = 16;
+pub type sgx_aes_siv_256bit_key_t = [uint8_t; SGX_AESSIV_KEY_SIZE];
+pub type sgx_aes_siv_iv_t = [uint8_t; SGX_AESSIV_IV_SIZE];
+
+fn aes_siv_dbl(block: &mut [u8; 16]) {
+    let carry = block[0] >> 7;
+    for i in 0..15 {
+        block[i] = (block[i] << 1) | (block[i + 1] >> 7);
+    }
+    block[15] = (block[15] << 1) ^ (0x87 & 0_u8.wrapping_sub(carry));
+}
+
+fn aes_siv_cmac(key: &sgx_cmac_128bit_key_t, parts: &[&[u8]]) -> SgxResult<sgx_cmac_128bit_tag_t> {
+    let handle = SgxCmacHandle::new();
+    handle.init(key)?;
+    for part in parts.iter().filter(|part| !part.is_empty()) {
+        handle.update_slice(part)?;
+    }
+    let mac = handle.get_hash()?;
+    handle.close()?;
+    Ok(mac)
+}
+
+fn aes_siv_s2v(
+    key: &sgx_cmac_128bit_key_t,
+    aad: &[&[u8]],
+    src: &[u8],
+) -> SgxResult<sgx_aes_siv_iv_t> {
+    let mut d = aes_siv_cmac(key, &[&[0_u8; 16]])?;
+    for s in aad.iter() {
+        aes_siv_dbl(&mut d);
+        let mac = aes_siv_cmac(key, &[s])?;
+        d.iter_mut().zip(mac.iter()).for_each(|(a, b)| *a ^= *b);
+    }
+
+    let mut last = [0_u8; 16];
+    let v = if src.len() >= 16 {
+        let (head, tail) = src.split_at(src.len() - 16);
+        last.copy_from_slice(tail);
+        last.iter_mut().zip(d.iter()).for_each(|(a, b)| *a ^= *b);
+        aes_siv_cmac(key, &[head, &last])
+    } else {
+        aes_siv_dbl(&mut d);
+        last[..src.len()].copy_from_slice(src);
+        last[src.len()] = 0x80;
+        last.iter_mut().zip(d.iter()).for_each(|(a, b)| *a ^= *b);
+        aes_siv_cmac(key, &[&last])
+    };
+    zero_memory(&mut last);
+    zero_memory(&mut d);
+    v
+}
+
+fn aes_siv_ctr(
+    key: &sgx_aes_siv_256bit_key_t,
+    siv: &sgx_aes_siv_iv_t,
+    src: &[u8],
+    dst: &mut [u8],
+) -> SgxError {
+    if src.is_empty() {
+        return Ok(());
+    }
+    let mut ctr_key = sgx_aes_ctr_128bit_key_t::default();
+    ctr_key.copy_from_slice(&key[16..]);
+    let mut ctr = *siv;
+    ctr[8] &= 0x7f;
+    ctr[12] &= 0x7f;
+    let result = rsgx_aes_ctr_encrypt(&ctr_key, src, &mut ctr, 128, dst);
+    zero_memory(&mut ctr_key);
+    result
+}
+
+///
+/// rsgx_aes_siv_encrypt performs an AES-SIV authenticated encryption operation.
+///
+/// # Description
+///
+/// This function implements the deterministic authenticated encryption mode of [RFC 5297]
+/// with AES-CMAC-128 and AES-CTR-128. The synthetic IV is derived from the associated data and
+/// the plaintext, so encrypting with a repeated (key, aad) pair only reveals whether the two
+/// plaintexts are equal. It is meant for sealing small secrets where a fresh nonce is hard to
+/// come by; if a nonce is available it should be passed as the last associated data string.
+///
+/// # Parameters
+///
+/// **key**
+///
+/// A pointer to the 256-bit key. The first half is the S2V (CMAC) key and the second half is
+/// the CTR key.
+///
+/// **aad**
+///
+/// The associated data strings, which are authenticated but not encrypted. At most 126
+/// strings may be passed; the slice may be empty.
+///
+/// **src**
+///
+/// A pointer to the input data stream to be encrypted. The buffer may be empty.
+///
+/// **dst**
+///
+/// A pointer to the output encrypted data buffer. Its size must be at least the size of src.
+///
+/// **siv**
+///
+/// The output synthetic IV, which doubles as the authentication tag and must be passed to
+/// rsgx_aes_siv_decrypt.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The output buffer is too small or too many associated data strings were given.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// Not enough memory is available to complete this operation.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_aes_siv_encrypt(
+    key: &sgx_aes_siv_256bit_key_t,
+    aad: &[&[u8]],
+    src: &[u8],
+    dst: &mut [u8],
+    siv: &mut sgx_aes_siv_iv_t,
+) -> SgxError {
+    if aad.len() > 126 || dst.len() < src.len() {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+
+    let mut mac_key = sgx_cmac_128bit_key_t::default();
+    mac_key.copy_from_slice(&key[..16]);
+    let result = aes_siv_s2v(&mac_key, aad, src);
+    zero_memory(&mut mac_key);
+
+    let v = result?;
+    aes_siv_ctr(key, &v, src, dst)?;
+    *siv = v;
+    Ok(())
+}
+
+///
+/// rsgx_aes_siv_decrypt performs an AES-SIV authenticated decryption operation.
 ///
 /// # Description
 ///
-/// This function decrypts the input data stream of a variable length according to
-/// the CTR mode as specified in [NIST SP 800-38A]. The counter can be thought
-/// of as an IV which increments on successive encryption or decryption calls. For
-/// a given dataset or data stream, the incremented counter block should be used
-/// on successive calls of the decryption process for that given stream. However,
-/// for new or different datasets/streams, the same counter should not be reused,
-/// instead initialize the counter for the new data set.
-///
-/// It is recommended that the source, destination and counter data buffers are
-/// allocated within the enclave.
+/// This function decrypts src with the counter derived from siv, recomputes the synthetic IV
+/// over aad and the recovered plaintext, and compares it with siv in constant time. If they
+/// differ the output buffer is zeroed and SGX_ERROR_MAC_MISMATCH is returned.
 ///
 /// # Parameters
 ///
 /// **key**
 ///
-/// A pointer to key to be used in the AES-CTR encryption operation. The size must be 128 bits.
+/// A pointer to the 256-bit key used by rsgx_aes_siv_encrypt.
 ///
-/// **src**
+/// **aad**
 ///
-/// A pointer to the input data stream to be decrypted.
+/// The associated data strings, in the same order as for encryption.
 ///
-/// **ctr**
+/// **src**
 ///
-/// A pointer to the initialization vector to be used in the AES-CTR calculation.
+/// A pointer to the input data stream to be decrypted. The buffer may be empty.
 ///
-/// **ctr_inc_bits**
+/// **siv**
 ///
-/// Specifies the number of bits in the counter to be incremented.
+/// The synthetic IV produced by rsgx_aes_siv_encrypt.
 ///
 /// **dst**
 ///
-/// A pointer to the output decrypted data buffer. This buffer should be allocated by the calling code.
+/// A pointer to the output decrypted data buffer. Its size must be at least the size of src.
 ///
 /// # Requirements
 ///
@@ -2072,7 +2860,11 @@ pub fn rsgx_aes_ctr_encrypt(
 ///
 /// **SGX_ERROR_INVALID_PARAMETER**
 ///
-/// The pointer is invalid.
+/// The output buffer is too small or too many associated data strings were given.
+///
+/// **SGX_ERROR_MAC_MISMATCH**
+///
+/// The input data could not be verified.
 ///
 /// **SGX_ERROR_OUT_OF_MEMORY**
 ///
@@ -2082,41 +2874,35 @@ pub fn rsgx_aes_ctr_encrypt(
 ///
 /// An internal cryptography library failure occurred.
 ///
-pub fn rsgx_aes_ctr_decrypt(
-    key: &sgx_aes_ctr_128bit_key_t,
+pub fn rsgx_aes_siv_decrypt(
+    key: &sgx_aes_siv_256bit_key_t,
+    aad: &[&[u8]],
     src: &[u8],
-    ctr: &mut sgx_aes_ctr_128bit_ctr_t,
-    ctr_inc_bits: u32,
+    siv: &sgx_aes_siv_iv_t,
     dst: &mut [u8],
 ) -> SgxError {
-    let src_len = src.len();
-    if src_len > u32::MAX as usize {
-        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
-    }
-    if src_len < 1 {
-        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
-    }
-    let dst_len = dst.len();
-    if dst_len > u32::MAX as usize {
-        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
-    }
-    if dst_len < src_len {
+    if aad.len() > 126 || dst.len() < src.len() {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
     }
 
-    let ret = unsafe {
-        sgx_aes_ctr_decrypt(
-            key as *const sgx_aes_ctr_128bit_key_t,
-            src.as_ptr(),
-            src.len() as u32,
-            ctr as *mut sgx_aes_ctr_128bit_ctr_t as *mut u8,
-            ctr_inc_bits,
-            dst.as_mut_ptr(),
-        )
-    };
-    match ret {
-        sgx_status_t::SGX_SUCCESS => Ok(()),
-        _ => Err(ret),
+    let dst = &mut dst[..src.len()];
+    aes_siv_ctr(key, siv, src, dst)?;
+
+    let mut mac_key = sgx_cmac_128bit_key_t::default();
+    mac_key.copy_from_slice(&key[..16]);
+    let result = aes_siv_s2v(&mac_key, aad, dst);
+    zero_memory(&mut mac_key);
+
+    match result {
+        Ok(v) if rsgx_ct_compare(&v, siv) => Ok(()),
+        Ok(_) => {
+            zero_memory(dst);
+            Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+        }
+        Err(e) => {
+            zero_memory(dst);
+            Err(e)
+        }
     }
 }
 
@@ -3644,6 +4430,9 @@ fn rsgx_rsa_pub_encrypt_sha256(
     }
 }
 
+// Largest modulus accepted by wrap_key/unwrap_key (RSA-4096).
+const RSA_WRAP_MAX_MOD_SIZE: usize = 512;
+
 pub struct SgxRsaPrivKey {
     key: RefCell<sgx_rsa_key_t>,
     mod_size: Cell<i32>,
@@ -3763,6 +4552,42 @@ impl SgxRsaPrivKey {
         }
     }
 
+    ///
+    /// unwrap_key decrypts a 128-bit key wrapped by SgxRsaPubKey::wrap_key.
+    ///
+    /// The wrapped key must be exactly as long as the modulus. The intermediate
+    /// plaintext buffer is zeroed before returning.
+    ///
+    pub fn unwrap_key(&self, wrapped: &[u8]) -> SgxResult<sgx_key_128bit_t> {
+        if !self.createflag.get() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        let mod_size = self.mod_size.get() as usize;
+        if mod_size > RSA_WRAP_MAX_MOD_SIZE || wrapped.len() != mod_size {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        let mut plain = [0_u8; RSA_WRAP_MAX_MOD_SIZE];
+        let mut plain_len = mod_size;
+        let ret = rsgx_rsa_priv_decrypt_sha256(
+            *self.key.borrow(),
+            &mut plain[..mod_size],
+            &mut plain_len,
+            wrapped,
+        );
+        let result = match ret {
+            sgx_status_t::SGX_SUCCESS if plain_len == mem::size_of::<sgx_key_128bit_t>() => {
+                let mut key = sgx_key_128bit_t::default();
+                key.copy_from_slice(&plain[..plain_len]);
+                Ok(key)
+            }
+            sgx_status_t::SGX_SUCCESS => Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+            _ => Err(ret),
+        };
+        zero_memory(&mut plain);
+        result
+    }
+
     pub fn free(&self) -> SgxError {
         if !self.createflag.get() {
             return Ok(());
@@ -3857,6 +4682,35 @@ impl SgxRsaPubKey {
         }
     }
 
+    ///
+    /// wrap_key encrypts a 128-bit key with RSA-OAEP (SHA-256) so that it can be
+    /// exported to the holder of the matching private key.
+    ///
+    /// out must be at least as long as the modulus. Returns the length of the
+    /// wrapped key, which is the modulus size.
+    ///
+    pub fn wrap_key(&self, key: &sgx_key_128bit_t, out: &mut [u8]) -> SgxResult<usize> {
+        if !self.createflag.get() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        let mod_size = self.mod_size.get() as usize;
+        if out.len() < mod_size {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        let mut out_len = mod_size;
+        let ret = rsgx_rsa_pub_encrypt_sha256(
+            *self.key.borrow(),
+            &mut out[..mod_size],
+            &mut out_len,
+            key,
+        );
+        match ret {
+            sgx_status_t::SGX_SUCCESS => Ok(out_len),
+            _ => Err(ret),
+        }
+    }
+
     pub fn free(&self) -> SgxError {
         if !self.createflag.get() {
             return Ok(());
@@ -4226,3 +5080,446 @@ impl Drop for SgxAesHandle {
         let _ = self.close();
     }
 }
+
+///
+/// The rsgx_ct_compare function compares two byte buffers in constant time.
+///
+/// # Description
+///
+/// The running time depends only on the length of the first buffer, not on the position of
+/// the first differing byte, so it is suitable for checking MACs and authentication tags.
+/// Buffers of different lengths compare unequal, but the comparison still runs to the end.
+///
+/// # Parameters
+///
+/// **a**
+///
+/// The first buffer, usually the expected tag.
+///
+/// **b**
+///
+/// The second buffer, usually the received tag.
+///
+/// # Return value
+///
+/// true if the buffers have the same length and contents, false otherwise.
+///
+pub fn rsgx_ct_compare(a: &[u8], b: &[u8]) -> bool {
+    let mut diff = 0_u8;
+    for (i, x) in a.iter().enumerate() {
+        let y = b.get(i).copied().unwrap_or(0);
+        diff |= x ^ y;
+    }
+    // Keep the compiler from turning the loop into an early-exit memcmp.
+    let diff = unsafe { ptr::read_volatile(&diff) };
+    (diff as usize | (a.len() ^ b.len())) == 0
+}
+
+///
+/// The rsgx_ct_select function picks one of two 128-bit keys without branching on the choice.
+///
+/// # Description
+///
+/// Both keys are read in full and combined with a mask derived from choice, so neither the
+/// running time nor the memory access pattern depends on which key is returned. Use it when
+/// the choice itself is secret, for example whether a key rotation has happened.
+///
+/// # Parameters
+///
+/// **choice**
+///
+/// Selects a when true and b when false.
+///
+/// **a**
+///
+/// The key returned when choice is true.
+///
+/// **b**
+///
+/// The key returned when choice is false.
+///
+/// # Return value
+///
+/// A copy of the selected key.
+///
+pub fn rsgx_ct_select(
+    choice: bool,
+    a: &sgx_key_128bit_t,
+    b: &sgx_key_128bit_t,
+) -> sgx_key_128bit_t {
+    // Keep the compiler from turning the mask back into a branch.
+    let mask = 0_u8.wrapping_sub(unsafe { ptr::read_volatile(&(choice as u8)) });
+    let mut out = sgx_key_128bit_t::default();
+    for ((o, x), y) in out.iter_mut().zip(a.iter()).zip(b.iter()) {
+        *o = y ^ (mask & (x ^ y));
+    }
+    out
+}
+
+///
+/// The rsgx_aes_block_encrypt function encrypts a single 16-byte block with AES-128.
+///
+/// # Description
+///
+/// This is the raw block cipher, the forward function E(K, X) of FIPS-197. It is a building
+/// block for modes such as CMAC subkeys, GHASH keys or SIV, not an encryption mode by itself:
+/// encrypting data block by block this way is ECB and leaks equal plaintext blocks. The
+/// block is computed by the IPP-based AES-CTR implementation of libsgx_tcrypto, which uses
+/// AES-NI.
+///
+/// libsgx_tcrypto only exposes the forward AES direction, so there is no matching
+/// decryption function; constructions that need the inverse cipher can't be built on it.
+///
+/// # Parameters
+///
+/// **key**
+///
+/// A pointer to the 128-bit AES key.
+///
+/// **block**
+///
+/// The plaintext block.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a
+///
+/// # Return value
+///
+/// The encrypted block.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The pointer is invalid.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// Not enough memory is available to complete this operation.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_aes_block_encrypt(
+    key: &sgx_aes_gcm_128bit_key_t,
+    block: &[u8; SGX_AESCTR_CTR_SIZE],
+) -> SgxResult<[u8; SGX_AESCTR_CTR_SIZE]> {
+    // E(K, X) is the first keystream block of CTR mode started at counter X.
+    let mut ctr = *block;
+    let mut out = [0_u8; SGX_AESCTR_CTR_SIZE];
+    let ret = rsgx_aes_ctr_encrypt(key, &[0_u8; SGX_AESCTR_CTR_SIZE], &mut ctr, 128, &mut out);
+    zero_memory(&mut ctr);
+    ret.map(|_| out)
+}
+
+// 2^32 - 2 blocks, the most GCM can process under one IV before the 32-bit counter
+// wraps back to J0 and the keystream repeats (NIST SP 800-38D, Section 5.2.1.1).
+const GCM_MAX_TEXT_LEN: u64 = ((1 << 32) - 2) * SGX_AESCTR_CTR_SIZE as u64;
+
+struct AesGcmDecState {
+    key: sgx_aes_gcm_128bit_key_t,
+    ctr: sgx_aes_ctr_128bit_ctr_t,
+    ek_j0: [u8; SGX_AESGCM_MAC_SIZE],
+    keystream: [u8; SGX_AESCTR_CTR_SIZE],
+    keystream_pos: usize,
+    ghash: GHash,
+    aad_len: u64,
+    text_len: u64,
+}
+
+impl AesGcmDecState {
+    fn new(key: &sgx_aes_gcm_128bit_key_t, iv: &[u8], aad: &[u8]) -> SgxResult<AesGcmDecState> {
+        let mut h = rsgx_aes_block_encrypt(key, &[0_u8; SGX_AESCTR_CTR_SIZE])?;
+        let mut j0 = [0_u8; SGX_AESCTR_CTR_SIZE];
+        j0[..SGX_AESGCM_IV_SIZE].copy_from_slice(iv);
+        j0[SGX_AESCTR_CTR_SIZE - 1] = 1;
+        let ek_j0 = rsgx_aes_block_encrypt(key, &j0)?;
+
+        let mut ctr = j0;
+        ctr[SGX_AESCTR_CTR_SIZE - 1] = 2;
+        let mut ghash = GHash::new(&h);
+        zero_memory(&mut h);
+        ghash.update(aad);
+        ghash.pad();
+
+        Ok(AesGcmDecState {
+            key: *key,
+            ctr,
+            ek_j0,
+            keystream: [0; SGX_AESCTR_CTR_SIZE],
+            keystream_pos: SGX_AESCTR_CTR_SIZE,
+            ghash,
+            aad_len: aad.len() as u64,
+            text_len: 0,
+        })
+    }
+
+    fn update(&mut self, src: &[u8], dst: &mut [u8]) -> SgxError {
+        let text_len = self.text_len + src.len() as u64;
+        if text_len > GCM_MAX_TEXT_LEN {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        self.ghash.update(src);
+        self.text_len = text_len;
+
+        let mut off = 0;
+        while off < src.len() && self.keystream_pos < SGX_AESCTR_CTR_SIZE {
+            dst[off] = src[off] ^ self.keystream[self.keystream_pos];
+            self.keystream_pos += 1;
+            off += 1;
+        }
+
+        let full = (src.len() - off) / SGX_AESCTR_CTR_SIZE * SGX_AESCTR_CTR_SIZE;
+        if full > 0 {
+            rsgx_aes_ctr_decrypt(
+                &self.key,
+                &src[off..off + full],
+                &mut self.ctr,
+                32,
+                &mut dst[off..off + full],
+            )?;
+            off += full;
+        }
+
+        if off < src.len() {
+            rsgx_aes_ctr_encrypt(
+                &self.key,
+                &[0_u8; SGX_AESCTR_CTR_SIZE],
+                &mut self.ctr,
+                32,
+                &mut self.keystream,
+            )?;
+            self.keystream_pos = 0;
+            while off < src.len() {
+                dst[off] = src[off] ^ self.keystream[self.keystream_pos];
+                self.keystream_pos += 1;
+                off += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn verify_mac(&self, mac: &sgx_aes_gcm_128bit_tag_t) -> SgxError {
+        let mut tag = self.ghash.clone().finalize(self.aad_len, self.text_len);
+        tag.iter_mut().zip(&self.ek_j0).for_each(|(t, e)| *t ^= e);
+        let equal = rsgx_ct_compare(&tag, mac);
+        zero_memory(&mut tag);
+        if equal {
+            Ok(())
+        } else {
+            Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+        }
+    }
+}
+
+impl Drop for AesGcmDecState {
+    fn drop(&mut self) {
+        zero_memory(&mut self.key);
+        zero_memory(&mut self.ctr);
+        zero_memory(&mut self.ek_j0);
+        zero_memory(&mut self.keystream);
+    }
+}
+
+///
+/// SgxAesGcmDecHandle performs a Rijndael AES-GCM decryption over multiple input data sets.
+///
+/// # Description
+///
+/// This is the decryption counterpart of SgxAesHandle. The ciphertext can be fed through
+/// update in chunks of any size; the GHASH state is carried across chunks, and verify_mac
+/// checks the authentication tag over all of the AAD and ciphertext that has been processed.
+/// Empty chunks are accepted and change nothing, so a streaming caller does not have to
+/// filter them out.
+///
+/// Plaintext is written to the caller's buffers as it is decrypted, before the tag can be
+/// checked, so it must not be used until verify_mac succeeds. verify_mac consumes the handle
+/// and takes the plaintext buffer: on a tag mismatch it zeroes that buffer before returning
+/// the error. Plaintext from update that was kept in other buffers is the caller's to wipe.
+/// The key and all intermediate state are scrubbed on close and when verify_mac returns.
+///
+/// Only a 128-bit key and a 96-bit IV are supported. A stream can be at most 2^32 - 2 blocks
+/// long; update returns SGX_ERROR_INVALID_PARAMETER for a chunk that would go past that.
+///
+pub struct SgxAesGcmDecHandle {
+    state: RefCell<Option<AesGcmDecState>>,
+}
+
+impl SgxAesGcmDecHandle {
+    pub fn new() -> SgxAesGcmDecHandle {
+        SgxAesGcmDecHandle {
+            state: RefCell::new(None),
+        }
+    }
+
+    pub fn init(&self, key: &sgx_aes_gcm_128bit_key_t, iv: &[u8], aad: &[u8]) -> SgxError {
+        if self.state.borrow().is_some() {
+            return Ok(());
+        }
+        if iv.len() != SGX_AESGCM_IV_SIZE {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        if aad.len() > u32::MAX as usize {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        let state = AesGcmDecState::new(key, iv, aad)?;
+        *self.state.borrow_mut() = Some(state);
+        Ok(())
+    }
+
+    pub fn update(&self, src: &[u8], dst: &mut [u8]) -> SgxError {
+        let mut state = self.state.borrow_mut();
+        let state = state.as_mut().ok_or(sgx_status_t::SGX_ERROR_INVALID_STATE)?;
+
+        let src_len = src.len();
+        if src_len > u32::MAX as usize {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        if src_len == 0 {
+            return Ok(());
+        }
+        if dst.len() < src_len {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        state.update(src, &mut dst[..src_len])
+    }
+
+    /// Checks `mac` against the AAD and all ciphertext passed to update.
+    ///
+    /// `plaintext` is the output of update. It is zeroed if the tag does not match, in which
+    /// case SGX_ERROR_MAC_MISMATCH is returned. Fails with SGX_ERROR_INVALID_STATE if the
+    /// handle was not initialized. A message with empty AAD and no ciphertext is valid and
+    /// is checked like any other, matching the tag SgxAesHandle::get_mac produces for it.
+    pub fn verify_mac(self, mac: &sgx_aes_gcm_128bit_tag_t, plaintext: &mut [u8]) -> SgxError {
+        let state = self.state.borrow_mut().take();
+        let state = state.ok_or(sgx_status_t::SGX_ERROR_INVALID_STATE)?;
+        let ret = state.verify_mac(mac);
+        if ret.is_err() {
+            zero_memory(plaintext);
+        }
+        ret
+    }
+
+    pub fn verify_align_mac(self, mac: &sgx_align_mac_128bit_t, plaintext: &mut [u8]) -> SgxError {
+        self.verify_mac(&mac.mac, plaintext)
+    }
+
+    pub fn close(&self) -> SgxError {
+        *self.state.borrow_mut() = None;
+        Ok(())
+    }
+}
+
+impl Default for SgxAesGcmDecHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SgxAesGcmDecHandle {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+/// AES-128 in CTR mode with random access to the keystream.
+///
+/// The whole 128-bit counter block is incremented as a big-endian integer, matching
+/// rsgx_aes_ctr_encrypt with ctr_inc_bits set to 128. seek positions the stream at a
+/// given 16-byte block, so a single node in the middle of a file can be decrypted without
+/// processing the data before it. Since CTR mode is symmetric, apply_keystream both
+/// encrypts and decrypts.
+pub struct SgxAesCtrHandle {
+    key: sgx_aes_ctr_128bit_key_t,
+    iv: sgx_aes_ctr_128bit_ctr_t,
+    ctr: sgx_aes_ctr_128bit_ctr_t,
+    keystream: [u8; SGX_AESCTR_CTR_SIZE],
+    keystream_pos: usize,
+}
+
+impl SgxAesCtrHandle {
+    const CHUNK_SIZE: usize = 256;
+
+    /// Creates a stream positioned at block 0, whose counter block is iv.
+    pub fn new(key: &sgx_aes_ctr_128bit_key_t, iv: &sgx_aes_ctr_128bit_ctr_t) -> SgxAesCtrHandle {
+        SgxAesCtrHandle {
+            key: *key,
+            iv: *iv,
+            ctr: *iv,
+            keystream: [0_u8; SGX_AESCTR_CTR_SIZE],
+            keystream_pos: SGX_AESCTR_CTR_SIZE,
+        }
+    }
+
+    /// Positions the stream at the start of the given block. The counter becomes
+    /// iv + block_index modulo 2^128.
+    pub fn seek(&mut self, block_index: u64) {
+        let ctr = u128::from_be_bytes(self.iv).wrapping_add(u128::from(block_index));
+        self.ctr = ctr.to_be_bytes();
+        zero_memory(&mut self.keystream);
+        self.keystream_pos = SGX_AESCTR_CTR_SIZE;
+    }
+
+    /// XORs the keystream at the current position into data and advances the position.
+    pub fn apply_keystream(&mut self, data: &mut [u8]) -> SgxError {
+        let mut off = 0;
+        while off < data.len() && self.keystream_pos < SGX_AESCTR_CTR_SIZE {
+            data[off] ^= self.keystream[self.keystream_pos];
+            self.keystream_pos += 1;
+            off += 1;
+        }
+
+        let mut buf = [0_u8; Self::CHUNK_SIZE];
+        let mut ret = Ok(());
+        while data.len() - off >= SGX_AESCTR_CTR_SIZE {
+            let len = cmp::min(
+                Self::CHUNK_SIZE,
+                (data.len() - off) / SGX_AESCTR_CTR_SIZE * SGX_AESCTR_CTR_SIZE,
+            );
+            buf[..len].copy_from_slice(&data[off..off + len]);
+            ret = rsgx_aes_ctr_encrypt(
+                &self.key,
+                &buf[..len],
+                &mut self.ctr,
+                128,
+                &mut data[off..off + len],
+            );
+            if ret.is_err() {
+                break;
+            }
+            off += len;
+        }
+        zero_memory(&mut buf);
+        ret?;
+
+        if off < data.len() {
+            rsgx_aes_ctr_encrypt(
+                &self.key,
+                &[0_u8; SGX_AESCTR_CTR_SIZE],
+                &mut self.ctr,
+                128,
+                &mut self.keystream,
+            )?;
+            self.keystream_pos = 0;
+            while off < data.len() {
+                data[off] ^= self.keystream[self.keystream_pos];
+                self.keystream_pos += 1;
+                off += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SgxAesCtrHandle {
+    fn drop(&mut self) {
+        zero_memory(&mut self.key);
+        zero_memory(&mut self.iv);
+        zero_memory(&mut self.ctr);
+        zero_memory(&mut self.keystream);
+    }
+}
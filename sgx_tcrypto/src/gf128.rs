@@ -0,0 +1,205 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! GF(2^128) arithmetic for GHASH (NIST SP 800-38D) and POLYVAL (RFC 8452), in Rust.
+//!
+//! libsgx_tcrypto computes GHASH only inside its AES-GCM functions and exports neither
+//! hash, so the streaming AES-GCM decryption handle, rsgx_ghash and AES-GCM-SIV all use
+//! this module. There is one multiplication, gf128_mul in the GCM bit order. POLYVAL is
+//! mapped onto it with the byte-reversal identity of RFC 8452, Appendix A, rather than
+//! having a field implementation of its own.
+//!
+//! The multiplication is bit-serial: operand bits are turned into masks instead of
+//! branches, and there are no lookup tables, so its running time and memory accesses do not
+//! depend on the key or the data. This is an argument from reading the code. The module has
+//! not had a constant-time audit or been checked with tools such as ctgrind, and it is
+//! several times slower than a table-driven or carry-less-multiply implementation.
+
+use crate::crypto::zero_memory;
+use core::cmp;
+use core::ptr;
+
+pub(crate) const GHASH_BLOCK_SIZE: usize = 16;
+
+// Multiplication by x in the GCM bit order, i.e. mulX_GHASH of RFC 8452, Appendix A.
+fn gf128_mulx(v: u128) -> u128 {
+    let lsb = v & 1;
+    (v >> 1) ^ ((0xe1_u128 << 120) & 0_u128.wrapping_sub(lsb))
+}
+
+fn gf128_mul(x: u128, y: u128) -> u128 {
+    // Multiplication in GF(2^128) with the GCM bit ordering and reduction
+    // polynomial (NIST SP 800-38D, Algorithm 1). Bits are selected with masks
+    // rather than branches so the running time does not depend on the operands.
+    let mut z = 0_u128;
+    let mut v = y;
+    for i in 0..128 {
+        let bit = (x >> (127 - i)) & 1;
+        z ^= v & 0_u128.wrapping_sub(bit);
+        v = gf128_mulx(v);
+    }
+    z
+}
+
+#[derive(Clone)]
+pub(crate) struct GHash {
+    h: u128,
+    x: u128,
+    buf: [u8; GHASH_BLOCK_SIZE],
+    buf_len: usize,
+}
+
+impl GHash {
+    pub(crate) fn new(h: &[u8; GHASH_BLOCK_SIZE]) -> GHash {
+        GHash::with_key(u128::from_be_bytes(*h))
+    }
+
+    fn with_key(h: u128) -> GHash {
+        GHash {
+            h,
+            x: 0,
+            buf: [0; GHASH_BLOCK_SIZE],
+            buf_len: 0,
+        }
+    }
+
+    pub(crate) fn update(&mut self, mut data: &[u8]) {
+        if self.buf_len > 0 {
+            let n = cmp::min(GHASH_BLOCK_SIZE - self.buf_len, data.len());
+            self.buf[self.buf_len..self.buf_len + n].copy_from_slice(&data[..n]);
+            self.buf_len += n;
+            data = &data[n..];
+            if self.buf_len < GHASH_BLOCK_SIZE {
+                return;
+            }
+            let block = self.buf;
+            self.process_block(&block);
+            self.buf_len = 0;
+        }
+
+        let mut blocks = data.chunks_exact(GHASH_BLOCK_SIZE);
+        for block in &mut blocks {
+            let mut b = [0_u8; GHASH_BLOCK_SIZE];
+            b.copy_from_slice(block);
+            self.process_block(&b);
+        }
+        let rem = blocks.remainder();
+        self.buf[..rem.len()].copy_from_slice(rem);
+        self.buf_len = rem.len();
+    }
+
+    // Zero-pads and absorbs any buffered partial block.
+    pub(crate) fn pad(&mut self) {
+        if self.buf_len > 0 {
+            self.buf[self.buf_len..].iter_mut().for_each(|b| *b = 0);
+            let block = self.buf;
+            self.process_block(&block);
+            self.buf_len = 0;
+        }
+    }
+
+    fn process_block(&mut self, block: &[u8; GHASH_BLOCK_SIZE]) {
+        self.x = gf128_mul(self.x ^ u128::from_be_bytes(*block), self.h);
+    }
+
+    pub(crate) fn finalize(mut self, aad_len: u64, text_len: u64) -> [u8; GHASH_BLOCK_SIZE] {
+        self.pad();
+        let mut lens = [0_u8; GHASH_BLOCK_SIZE];
+        lens[..8].copy_from_slice(&(aad_len * 8).to_be_bytes());
+        lens[8..].copy_from_slice(&(text_len * 8).to_be_bytes());
+        self.process_block(&lens);
+        self.x.to_be_bytes()
+    }
+}
+
+impl Drop for GHash {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::write_volatile(&mut self.h, 0);
+            ptr::write_volatile(&mut self.x, 0);
+        }
+        zero_memory(&mut self.buf);
+    }
+}
+
+// POLYVAL through GHASH (RFC 8452, Appendix A):
+// POLYVAL(H, X_1, ..., X_n) =
+//     ByteReverse(GHASH(mulX_GHASH(ByteReverse(H)), ByteReverse(X_1), ..., ByteReverse(X_n)))
+pub(crate) struct Polyval(GHash);
+
+impl Polyval {
+    pub(crate) fn new(h: &[u8; GHASH_BLOCK_SIZE]) -> Polyval {
+        Polyval(GHash::with_key(gf128_mulx(u128::from_le_bytes(*h))))
+    }
+
+    // Absorbs data as whole blocks, zero-padding the last one.
+    pub(crate) fn update_padded(&mut self, data: &[u8]) {
+        let mut block = [0_u8; GHASH_BLOCK_SIZE];
+        for chunk in data.chunks(GHASH_BLOCK_SIZE) {
+            block = [0_u8; GHASH_BLOCK_SIZE];
+            block[..chunk.len()].copy_from_slice(chunk);
+            block.reverse();
+            self.0.process_block(&block);
+        }
+        zero_memory(&mut block);
+    }
+
+    pub(crate) fn finalize(self) -> [u8; GHASH_BLOCK_SIZE] {
+        self.0.x.to_le_bytes()
+    }
+}
+
+///
+/// The rsgx_ghash function computes the GHASH universal hash of GCM.
+///
+/// # Description
+///
+/// This is GHASH_H(X) from NIST SP 800-38D, Algorithm 2, with the bit ordering of the GCM
+/// specification. Unlike in rsgx_rijndael128GCM_encrypt, no length block is appended: the
+/// caller lays out X however its construction requires. The field multiplication is the
+/// one POLYVAL in rsgx_aes_gcm_siv_encrypt uses; see the notes on timing in the module.
+///
+/// GHASH is only defined for inputs that are a whole number of 16-byte blocks, and callers
+/// that implement a standard construction should pass such input. Any other length is
+/// accepted and the last block is padded with zeros, so the result is GHASH of the padded
+/// input. That makes data and data followed by up to 15 zero bytes hash to the same value;
+/// a construction that needs to tell them apart must encode the length itself, as GCM does
+/// with its final length block. An empty input returns the zero block.
+///
+/// This is a building block for custom constructions and for checking intermediate values.
+/// GHASH on its own is not a MAC.
+///
+/// # Parameters
+///
+/// **h**
+///
+/// The hash subkey H, for GCM the encryption of the zero block under the cipher key.
+///
+/// **data**
+///
+/// The input X.
+///
+/// # Return value
+///
+/// The 128-bit GHASH value.
+///
+pub fn rsgx_ghash(h: &[u8; 16], data: &[u8]) -> [u8; 16] {
+    let mut ghash = GHash::new(h);
+    ghash.update(data);
+    ghash.pad();
+    ghash.x.to_be_bytes()
+}
@@ -0,0 +1,451 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Comparison and derivation of 128-bit keys.
+//!
+//! libsgx_tcrypto has no entry points for either, so both are written here in Rust. The
+//! derivation functions reproduce the key derivation of Intel's protected file system
+//! library: one AES-128-CMAC, keyed with the parent key, over a sgx_pfs_kdf_input_t for each
+//! 128-bit block of output. The CMAC itself is computed by libsgx_tcrypto. Given the same
+//! parent key, label, node number and nonce, rsgx_pfs_derive_key returns the key that
+//! libsgx_tprotected_fs derives.
+
+use crate::crypto::{rsgx_rijndael128_cmac_msg, zero_memory};
+use core::mem;
+use core::ptr;
+use core::slice;
+use sgx_types::*;
+
+/// The label libsgx_tprotected_fs uses for the metadata key.
+pub const SGX_PFS_METADATA_KEY_LABEL: &[u8] = b"SGX-PROTECTED-FS-METADATA-KEY";
+/// The label libsgx_tprotected_fs uses for the session master key.
+pub const SGX_PFS_MASTER_KEY_LABEL: &[u8] = b"SGX-PROTECTED-FS-MASTER-KEY";
+/// The label libsgx_tprotected_fs uses for the per-node keys.
+pub const SGX_PFS_RANDOM_KEY_LABEL: &[u8] = b"SGX-PROTECTED-FS-RANDOM-KEY";
+/// A label for a long-lived per-file data key.
+///
+/// libsgx_tprotected_fs never derives a data key. Its node keys come from the session master
+/// key under SGX_PFS_RANDOM_KEY_LABEL, so keys under this label are separate from every key
+/// the C library derives from the same parent key.
+pub const SGX_PFS_DATA_KEY_LABEL: &[u8] = b"SGX-PROTECTED-FS-DATA-KEY";
+
+const SGX_PFS_DOMAIN_SEPARATOR: u8 = b'/';
+
+///
+/// The rsgx_key128_ct_eq function compares two 128-bit keys in constant time.
+///
+/// # Description
+///
+/// All 16 bytes are always compared, so the running time does not depend on the position of
+/// the first differing byte. Use it instead of == whenever one of the keys is secret, for
+/// example to check a restored key against the one it should match.
+///
+/// # Parameters
+///
+/// **a**
+///
+/// The first key.
+///
+/// **b**
+///
+/// The second key.
+///
+/// # Return value
+///
+/// true if the keys are equal, false otherwise.
+///
+pub fn rsgx_key128_ct_eq(a: &sgx_key_128bit_t, b: &sgx_key_128bit_t) -> bool {
+    let mut diff = 0_u8;
+    for (x, y) in a.iter().zip(b.iter()) {
+        diff |= x ^ y;
+    }
+    // Keep the compiler from turning the loop into an early-exit memcmp.
+    unsafe { ptr::read_volatile(&diff) == 0 }
+}
+
+///
+/// The rsgx_pfs_derive_key function derives a 128-bit key the way the protected file system does.
+///
+/// # Description
+///
+/// The key is the AES-128-CMAC of a sgx_pfs_kdf_input_t with index 1 and output_len 0x80,
+/// keyed with the parent key. The nonce is usually the key id that is stored next to the
+/// data, so that the key can be derived again when the data is read back. Use
+/// rsgx_pfs_derive_key_random to derive a new key with a fresh nonce.
+///
+/// # Parameters
+///
+/// **key**
+///
+/// The parent key.
+///
+/// **label**
+///
+/// The label, for example SGX_PFS_METADATA_KEY_LABEL. It is NUL-padded to 64 bytes.
+///
+/// **node_number**
+///
+/// The node number, 0 for keys that are not bound to a node.
+///
+/// **nonce**
+///
+/// The nonce.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a
+///
+/// # Return value
+///
+/// The derived key.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The label is empty or longer than 64 bytes.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// Not enough memory is available to complete this operation.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_pfs_derive_key(
+    key: &sgx_key_128bit_t,
+    label: &[u8],
+    node_number: u64,
+    nonce: &sgx_key_id_t,
+) -> SgxResult<sgx_key_128bit_t> {
+    let mut derived = sgx_key_128bit_t::default();
+    derive_key_sized(key, label, node_number, nonce, &mut derived)?;
+    Ok(derived)
+}
+
+///
+/// The rsgx_pfs_derive_key256 function derives a 256-bit key from the same input as rsgx_pfs_derive_key.
+///
+/// # Description
+///
+/// The key is two AES-128-CMAC blocks, computed in the counter mode of NIST SP 800-108: the
+/// first with index 1 and the second with index 2, both with output_len 0x100. Because
+/// output_len is part of the input, the first half is not the 128-bit key that
+/// rsgx_pfs_derive_key returns for the same parameters.
+///
+/// # Parameters
+///
+/// **key**
+///
+/// The parent key.
+///
+/// **label**
+///
+/// The label. It is NUL-padded to 64 bytes.
+///
+/// **node_number**
+///
+/// The node number, 0 for keys that are not bound to a node.
+///
+/// **nonce**
+///
+/// The nonce.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a
+///
+/// # Return value
+///
+/// The derived key.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The label is empty or longer than 64 bytes.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// Not enough memory is available to complete this operation.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_pfs_derive_key256(
+    key: &sgx_key_128bit_t,
+    label: &[u8],
+    node_number: u64,
+    nonce: &sgx_key_id_t,
+) -> SgxResult<sgx_key_256bit_t> {
+    let mut derived = sgx_key_256bit_t::default();
+    derive_key_sized(key, label, node_number, nonce, &mut derived)?;
+    Ok(derived)
+}
+
+///
+/// The rsgx_pfs_derive_key_random function derives a 128-bit key with a fresh random nonce.
+///
+/// # Description
+///
+/// The nonce is read from sgx_read_rand and the key is derived from it with
+/// rsgx_pfs_derive_key. Both are returned. The nonce is not secret and has to be stored
+/// with whatever the key protects, because it is needed to derive the key again.
+///
+/// # Parameters
+///
+/// **key**
+///
+/// The parent key.
+///
+/// **label**
+///
+/// The label. It is NUL-padded to 64 bytes.
+///
+/// **node_number**
+///
+/// The node number, 0 for keys that are not bound to a node.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a, and libsgx_trts.a for sgx_read_rand
+///
+/// # Return value
+///
+/// The derived key and the nonce it was derived with.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The label is empty or longer than 64 bytes.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// sgx_read_rand failed, or an internal cryptography library failure occurred.
+///
+pub fn rsgx_pfs_derive_key_random(
+    key: &sgx_key_128bit_t,
+    label: &[u8],
+    node_number: u64,
+) -> SgxResult<(sgx_key_128bit_t, sgx_key_id_t)> {
+    let mut nonce = sgx_key_id_t::default();
+    let ret = unsafe { sgx_read_rand(nonce.id.as_mut_ptr(), nonce.id.len()) };
+    if ret != sgx_status_t::SGX_SUCCESS {
+        return Err(ret);
+    }
+    let derived = rsgx_pfs_derive_key(key, label, node_number, &nonce)?;
+    Ok((derived, nonce))
+}
+
+///
+/// The rsgx_pfs_derive_keys_random function derives one 128-bit key per node number, each with a fresh random nonce.
+///
+/// # Description
+///
+/// This gives the same result as calling rsgx_pfs_derive_key_random once per node number,
+/// but all the nonces are read with a single sgx_read_rand call. Entry i of keys and
+/// nonces belongs to node_numbers\[i\]. If the function fails, every entry of keys is
+/// zeroed.
+///
+/// # Parameters
+///
+/// **key**
+///
+/// The parent key.
+///
+/// **label**
+///
+/// The label. It is NUL-padded to 64 bytes.
+///
+/// **node_numbers**
+///
+/// The node numbers to derive keys for.
+///
+/// **keys**
+///
+/// Receives the derived keys. It must have the same length as node_numbers.
+///
+/// **nonces**
+///
+/// Receives the nonces. It must have the same length as node_numbers.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a, and libsgx_trts.a for sgx_read_rand
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The label is empty or longer than 64 bytes, or keys or nonces do not have the length of
+/// node_numbers.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// sgx_read_rand failed, or an internal cryptography library failure occurred.
+///
+pub fn rsgx_pfs_derive_keys_random(
+    key: &sgx_key_128bit_t,
+    label: &[u8],
+    node_numbers: &[u64],
+    keys: &mut [sgx_key_128bit_t],
+    nonces: &mut [sgx_key_id_t],
+) -> SgxError {
+    if keys.len() != node_numbers.len() || nonces.len() != node_numbers.len() {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    if node_numbers.is_empty() {
+        return Ok(());
+    }
+    let ret = unsafe {
+        sgx_read_rand(nonces.as_mut_ptr() as *mut u8, mem::size_of_val(nonces))
+    };
+    if ret != sgx_status_t::SGX_SUCCESS {
+        return Err(ret);
+    }
+    for i in 0..node_numbers.len() {
+        if let Err(e) = derive_key_sized(key, label, node_numbers[i], &nonces[i], &mut keys[i]) {
+            for k in keys.iter_mut() {
+                zero_memory(k);
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+///
+/// The rsgx_pfs_derive_key_in_domain function derives a 128-bit key under a label that is extended with a domain.
+///
+/// # Description
+///
+/// The label field holds the label, a '/' and then the domain, so different domains give
+/// independent keys from the same parent key, node number and nonce. This lets several
+/// subsystems share one parent key without sharing derived keys. There is no domain that
+/// gives the key of the plain label.
+///
+/// # Parameters
+///
+/// **key**
+///
+/// The parent key.
+///
+/// **label**
+///
+/// The label.
+///
+/// **domain**
+///
+/// The domain. It must not be empty or contain a NUL byte.
+///
+/// **node_number**
+///
+/// The node number, 0 for keys that are not bound to a node.
+///
+/// **nonce**
+///
+/// The nonce.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a
+///
+/// # Return value
+///
+/// The derived key.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The label or the domain is empty, the domain contains a NUL byte, or the label, the '/'
+/// and the domain together are longer than 64 bytes.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// Not enough memory is available to complete this operation.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_pfs_derive_key_in_domain(
+    key: &sgx_key_128bit_t,
+    label: &[u8],
+    domain: &[u8],
+    node_number: u64,
+    nonce: &sgx_key_id_t,
+) -> SgxResult<sgx_key_128bit_t> {
+    let len = label.len() + 1 + domain.len();
+    if label.is_empty() || domain.is_empty() || domain.contains(&0) || len > SGX_PFS_KDF_LABEL_MAX_LEN {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let mut full_label = [0_u8; SGX_PFS_KDF_LABEL_MAX_LEN];
+    full_label[..label.len()].copy_from_slice(label);
+    full_label[label.len()] = SGX_PFS_DOMAIN_SEPARATOR;
+    full_label[label.len() + 1..len].copy_from_slice(domain);
+    rsgx_pfs_derive_key(key, &full_label[..len], node_number, nonce)
+}
+
+// Fills out with one CMAC block per 16 bytes. The input is wiped before returning, and out
+// is wiped if a CMAC fails.
+fn derive_key_sized(
+    key: &sgx_key_128bit_t,
+    label: &[u8],
+    node_number: u64,
+    nonce: &sgx_key_id_t,
+    out: &mut [u8],
+) -> SgxError {
+    if label.is_empty() || label.len() > SGX_PFS_KDF_LABEL_MAX_LEN {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+
+    let mut input = sgx_pfs_kdf_input_t::default();
+    input.label[..label.len()].copy_from_slice(label);
+    input.node_number = node_number;
+    input.nonce = *nonce;
+    input.output_len = (out.len() * 8) as uint32_t;
+
+    let mut ret = Ok(());
+    for (i, block) in out.chunks_mut(SGX_CMAC_MAC_SIZE).enumerate() {
+        input.index = i as uint32_t + 1;
+        match rsgx_rijndael128_cmac_msg(key, &input) {
+            Ok(mut mac) => {
+                block.copy_from_slice(&mac);
+                zero_memory(&mut mac);
+            }
+            Err(e) => {
+                ret = Err(e);
+                break;
+            }
+        }
+    }
+
+    zero_memory(unsafe {
+        slice::from_raw_parts_mut(
+            &mut input as *mut sgx_pfs_kdf_input_t as *mut u8,
+            mem::size_of::<sgx_pfs_kdf_input_t>(),
+        )
+    });
+    if ret.is_err() {
+        zero_memory(out);
+    }
+    ret
+}
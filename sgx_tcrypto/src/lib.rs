@@ -31,3 +31,18 @@ extern crate sgx_types;
 
 mod crypto;
 pub use self::crypto::*;
+
+mod keys;
+pub use self::keys::*;
+
+mod sha512;
+pub use self::sha512::*;
+
+mod gf128;
+pub use self::gf128::*;
+
+mod gcm_siv;
+pub use self::gcm_siv::*;
+
+mod x25519;
+pub use self::x25519::*;
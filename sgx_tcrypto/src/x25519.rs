@@ -0,0 +1,364 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! X25519 key agreement (RFC 7748), implemented in Rust.
+//!
+//! This is not a libsgx_tcrypto wrapper. The SDK's elliptic-curve functions (sgx_ecc256_*)
+//! only cover NIST P-256, and there is no Curve25519 primitive to bind, so the field
+//! arithmetic and the ladder are written here: GF(2^255 - 19) in five 51-bit limbs on u64
+//! with u128 products, a Montgomery ladder over all 255 scalar bits, and inversion by a
+//! fixed exponentiation. Only rsgx_x25519_keypair calls into the SDK, for sgx_read_rand.
+//!
+//! The code follows the usual constant-time structure: the ladder runs the same operations
+//! for every scalar, conditional swaps use masks, and there are no branches or memory
+//! indices that depend on secret data. That is a property of the source as written. The
+//! module has not had a constant-time audit, it has not been checked with tools such as
+//! ctgrind, and nothing stops the compiler from turning a mask back into a branch. It is
+//! tested against the RFC 7748 vectors, including the iterated ones.
+
+use crate::crypto::zero_memory;
+use core::ptr;
+use core::sync::atomic::{self, Ordering};
+use sgx_types::*;
+
+const X25519_KEY_SIZE: usize = 32;
+const FE_MASK: u64 = (1 << 51) - 1;
+
+// An element of GF(2^255 - 19) in radix 2^51. Every operation returns limbs below 2^52,
+// which keeps the products in mul within u128 and the final carry within u64.
+#[derive(Clone, Copy)]
+struct Fe25519([u64; 5]);
+
+impl Fe25519 {
+    const ZERO: Fe25519 = Fe25519([0; 5]);
+    const ONE: Fe25519 = Fe25519([1, 0, 0, 0, 0]);
+
+    // Decodes a little-endian u-coordinate. The top bit is ignored, as RFC 7748
+    // requires, and non-canonical values above p are accepted.
+    fn from_bytes(b: &[u8; X25519_KEY_SIZE]) -> Fe25519 {
+        let load = |i: usize| {
+            let mut w = [0_u8; 8];
+            w.copy_from_slice(&b[i..i + 8]);
+            u64::from_le_bytes(w)
+        };
+        Fe25519([
+            load(0) & FE_MASK,
+            (load(6) >> 3) & FE_MASK,
+            (load(12) >> 6) & FE_MASK,
+            (load(19) >> 1) & FE_MASK,
+            (load(24) >> 12) & FE_MASK,
+        ])
+    }
+
+    // Encodes the value fully reduced modulo p.
+    fn to_bytes(self) -> [u8; X25519_KEY_SIZE] {
+        let mut l = self.carry().0;
+        // l < 2^255 + 2^13 here, so it is at most one p too large: q is 1 exactly when
+        // l + 19 overflows 2^255.
+        let mut q = (l[0] + 19) >> 51;
+        for limb in &l[1..] {
+            q = (limb + q) >> 51;
+        }
+        l[0] += 19 * q;
+        for i in 0..4 {
+            l[i + 1] += l[i] >> 51;
+            l[i] &= FE_MASK;
+        }
+        l[4] &= FE_MASK;
+
+        let mut out = [0_u8; X25519_KEY_SIZE];
+        let mut acc = 0_u128;
+        let mut bits = 0;
+        let mut pos = 0;
+        for limb in l.iter() {
+            acc |= u128::from(*limb) << bits;
+            bits += 51;
+            while bits >= 8 && pos < X25519_KEY_SIZE {
+                out[pos] = acc as u8;
+                acc >>= 8;
+                bits -= 8;
+                pos += 1;
+            }
+        }
+        out[pos] = acc as u8;
+        out
+    }
+
+    fn carry(self) -> Fe25519 {
+        let mut l = self.0;
+        for i in 0..4 {
+            l[i + 1] += l[i] >> 51;
+            l[i] &= FE_MASK;
+        }
+        l[0] += 19 * (l[4] >> 51);
+        l[4] &= FE_MASK;
+        Fe25519(l)
+    }
+
+    fn add(&self, b: &Fe25519) -> Fe25519 {
+        let mut l = [0_u64; 5];
+        for (i, limb) in l.iter_mut().enumerate() {
+            *limb = self.0[i] + b.0[i];
+        }
+        Fe25519(l).carry()
+    }
+
+    fn sub(&self, b: &Fe25519) -> Fe25519 {
+        // Adding 16p first keeps every limb positive.
+        const P16: [u64; 5] = [
+            16 * ((1 << 51) - 19),
+            16 * FE_MASK,
+            16 * FE_MASK,
+            16 * FE_MASK,
+            16 * FE_MASK,
+        ];
+        let mut l = [0_u64; 5];
+        for (i, limb) in l.iter_mut().enumerate() {
+            *limb = self.0[i] + P16[i] - b.0[i];
+        }
+        Fe25519(l).carry()
+    }
+
+    fn mul(&self, b: &Fe25519) -> Fe25519 {
+        let m = |x: u64, y: u64| u128::from(x) * u128::from(y);
+        let a = &self.0;
+        let b = &b.0;
+        let b1 = b[1] * 19;
+        let b2 = b[2] * 19;
+        let b3 = b[3] * 19;
+        let b4 = b[4] * 19;
+
+        let r0 = m(a[0], b[0]) + m(a[4], b1) + m(a[3], b2) + m(a[2], b3) + m(a[1], b4);
+        let mut r1 = m(a[1], b[0]) + m(a[0], b[1]) + m(a[4], b2) + m(a[3], b3) + m(a[2], b4);
+        let mut r2 = m(a[2], b[0]) + m(a[1], b[1]) + m(a[0], b[2]) + m(a[4], b3) + m(a[3], b4);
+        let mut r3 = m(a[3], b[0]) + m(a[2], b[1]) + m(a[1], b[2]) + m(a[0], b[3]) + m(a[4], b4);
+        let mut r4 = m(a[4], b[0]) + m(a[3], b[1]) + m(a[2], b[2]) + m(a[1], b[3]) + m(a[0], b[4]);
+
+        r1 += r0 >> 51;
+        r2 += r1 >> 51;
+        r3 += r2 >> 51;
+        r4 += r3 >> 51;
+        let mut l = [
+            (r0 as u64) & FE_MASK,
+            (r1 as u64) & FE_MASK,
+            (r2 as u64) & FE_MASK,
+            (r3 as u64) & FE_MASK,
+            (r4 as u64) & FE_MASK,
+        ];
+        l[0] += 19 * ((r4 >> 51) as u64);
+        l[1] += l[0] >> 51;
+        l[0] &= FE_MASK;
+        Fe25519(l)
+    }
+
+    fn square(&self) -> Fe25519 {
+        self.mul(self)
+    }
+
+    fn mul_small(&self, k: u32) -> Fe25519 {
+        let mut r = [0_u128; 5];
+        for (i, limb) in r.iter_mut().enumerate() {
+            *limb = u128::from(self.0[i]) * u128::from(k);
+        }
+        for i in 0..4 {
+            r[i + 1] += r[i] >> 51;
+            r[i] &= u128::from(FE_MASK);
+        }
+        let mut l = [0_u64; 5];
+        for (i, limb) in l.iter_mut().enumerate() {
+            *limb = (r[i] as u64) & FE_MASK;
+        }
+        l[0] += 19 * ((r[4] >> 51) as u64);
+        Fe25519(l).carry()
+    }
+
+    // a^(p - 2). The exponent 2^255 - 21 is public, so branching on its bits is safe;
+    // its only zero bits are 2 and 4.
+    fn invert(&self) -> Fe25519 {
+        let mut r = Fe25519::ONE;
+        for i in (0..255).rev() {
+            r = r.square();
+            if i != 2 && i != 4 {
+                r = r.mul(self);
+            }
+        }
+        r
+    }
+
+    fn cswap(a: &mut Fe25519, b: &mut Fe25519, swap: u64) {
+        let mask = 0_u64.wrapping_sub(swap);
+        for i in 0..5 {
+            let t = mask & (a.0[i] ^ b.0[i]);
+            a.0[i] ^= t;
+            b.0[i] ^= t;
+        }
+    }
+
+    fn clear(&mut self) {
+        for limb in self.0.iter_mut() {
+            unsafe { ptr::write_volatile(limb, 0) };
+        }
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+///
+/// The rsgx_x25519 function computes the X25519 function of RFC 7748.
+///
+/// # Description
+///
+/// This is the Montgomery-ladder scalar multiplication on Curve25519. The scalar is clamped
+/// as specified in RFC 7748, Section 5, and the most significant bit of the u-coordinate is
+/// ignored. The ladder does the same field operations for every scalar and selects values
+/// with masks; the module documentation says how far that can be relied on. Most callers
+/// want rsgx_x25519_keypair and rsgx_x25519_shared_secret instead.
+///
+/// # Parameters
+///
+/// **scalar**
+///
+/// The 32-byte scalar, usually a private key.
+///
+/// **u**
+///
+/// The 32-byte u-coordinate of the input point, usually a peer's public key.
+///
+/// # Return value
+///
+/// The u-coordinate of the result. It is all zeros when u is a point of small order.
+///
+pub fn rsgx_x25519(scalar: &[u8; 32], u: &[u8; 32]) -> [u8; 32] {
+    let mut k = *scalar;
+    k[0] &= 248;
+    k[31] &= 127;
+    k[31] |= 64;
+
+    let x1 = Fe25519::from_bytes(u);
+    let mut x2 = Fe25519::ONE;
+    let mut z2 = Fe25519::ZERO;
+    let mut x3 = x1;
+    let mut z3 = Fe25519::ONE;
+    let mut swap = 0_u64;
+
+    for t in (0..255).rev() {
+        let bit = u64::from((k[t / 8] >> (t % 8)) & 1);
+        swap ^= bit;
+        Fe25519::cswap(&mut x2, &mut x3, swap);
+        Fe25519::cswap(&mut z2, &mut z3, swap);
+        swap = bit;
+
+        let a = x2.add(&z2);
+        let aa = a.square();
+        let b = x2.sub(&z2);
+        let bb = b.square();
+        let e = aa.sub(&bb);
+        let c = x3.add(&z3);
+        let d = x3.sub(&z3);
+        let da = d.mul(&a);
+        let cb = c.mul(&b);
+        x3 = da.add(&cb).square();
+        z3 = x1.mul(&da.sub(&cb).square());
+        x2 = aa.mul(&bb);
+        z2 = e.mul(&aa.add(&e.mul_small(121_665)));
+    }
+    Fe25519::cswap(&mut x2, &mut x3, swap);
+    Fe25519::cswap(&mut z2, &mut z3, swap);
+
+    let out = x2.mul(&z2.invert()).to_bytes();
+    zero_memory(&mut k);
+    x2.clear();
+    z2.clear();
+    x3.clear();
+    z3.clear();
+    out
+}
+
+///
+/// The rsgx_x25519_keypair function generates an X25519 key pair.
+///
+/// # Description
+///
+/// The private key is 32 bytes from sgx_read_rand, and the public key is the X25519 function
+/// of it with the base point u = 9. The private key is returned unclamped; rsgx_x25519 clamps
+/// it on every use. The private key should be used for a single exchange and then dropped.
+///
+/// # Requirements
+///
+/// Library: libsgx_trts.a, for sgx_read_rand. Nothing is taken from libsgx_tcrypto.
+///
+/// # Return value
+///
+/// The private key and the public key.
+///
+/// # Errors
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// The random number generator failed.
+///
+pub fn rsgx_x25519_keypair() -> SgxResult<([u8; 32], [u8; 32])> {
+    let mut private = [0_u8; X25519_KEY_SIZE];
+    let ret = unsafe { sgx_read_rand(private.as_mut_ptr(), private.len()) };
+    if ret != sgx_status_t::SGX_SUCCESS {
+        return Err(ret);
+    }
+    let mut base = [0_u8; X25519_KEY_SIZE];
+    base[0] = 9;
+    let public = rsgx_x25519(&private, &base);
+    Ok((private, public))
+}
+
+///
+/// The rsgx_x25519_shared_secret function computes an X25519 shared secret.
+///
+/// # Description
+///
+/// Both sides of an exchange get the same 32 bytes from their own private key and the other
+/// side's public key. The shared secret is not uniformly random and must not be used as a key
+/// directly. Derive keys from it with rsgx_hkdf_sha256_extract and rsgx_hkdf_sha256_expand,
+/// for example a 128-bit key by expanding to 16 bytes with an info string that names
+/// its purpose.
+///
+/// # Parameters
+///
+/// **private**
+///
+/// This side's private key, as returned by rsgx_x25519_keypair.
+///
+/// **peer_public**
+///
+/// The other side's public key.
+///
+/// # Return value
+///
+/// The 32-byte shared secret.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The peer's public key is a point of small order, so the result would be all zeros
+/// whatever the private key.
+///
+pub fn rsgx_x25519_shared_secret(private: &[u8; 32], peer_public: &[u8; 32]) -> SgxResult<[u8; 32]> {
+    let mut shared = rsgx_x25519(private, peer_public);
+    if shared.iter().fold(0_u8, |acc, b| acc | b) == 0 {
+        zero_memory(&mut shared);
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    Ok(shared)
+}
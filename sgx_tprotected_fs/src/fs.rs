@@ -26,7 +26,20 @@ fn max_len() -> usize {
     u32::MAX as usize
 }
 
+fn is_zero_key(key: &sgx_key_128bit_t) -> bool {
+    // Accumulate without early return, so the check takes the same time for
+    // every key and does not leak where the first non-zero byte is.
+    key.iter().fold(0_u8, |acc, b| acc | b) == 0
+}
+
 unsafe fn rsgx_fopen(filename: &CStr, mode: &CStr, key: &sgx_key_128bit_t) -> SysResult<SGX_FILE> {
+    if is_zero_key(key) && rsgx_fopen_writes(mode) {
+        return Err(libc::EINVAL);
+    }
+    rsgx_fopen_weak_key(filename, mode, key)
+}
+
+unsafe fn rsgx_fopen_weak_key(filename: &CStr, mode: &CStr, key: &sgx_key_128bit_t) -> SysResult<SGX_FILE> {
     let file = sgx_fopen(
         filename.as_ptr(),
         mode.as_ptr(),
@@ -39,6 +52,17 @@ unsafe fn rsgx_fopen(filename: &CStr, mode: &CStr, key: &sgx_key_128bit_t) -> Sy
     }
 }
 
+// Whether opening with `mode` gives a handle that can write, which is what
+// can create a file or add data under `key`. This is decided from the mode
+// alone: whether the file exists is only known to the real open.
+fn rsgx_fopen_writes(mode: &CStr) -> bool {
+    let mode = mode.to_bytes();
+    match mode.first() {
+        Some(b'w') | Some(b'a') => true,
+        _ => mode.contains(&b'+'),
+    }
+}
+
 unsafe fn rsgx_fopen_auto_key(filename: &CStr, mode: &CStr) -> SysResult<SGX_FILE> {
     let file = sgx_fopen_auto_key(filename.as_ptr(), mode.as_ptr());
     if file.is_null() {
@@ -224,7 +248,9 @@ impl SgxFileStream {
     ///
     /// The encryption key of the file. This key is used as a key derivation key, used for deriving encryption
     /// keys for the file. If the file is created with open, you should protect this key and provide it as
-    /// input every time the file is opened.
+    /// input every time the file is opened. An all-zero key is rejected with EINVAL for every mode that
+    /// can write (‘w’, ‘a’ or any mode with ‘+’). Existing files written with an all-zero key can still
+    /// be opened with ‘r’; use open_weak_key to create or modify one.
     ///
     /// # Requirements
     ///
@@ -241,6 +267,24 @@ impl SgxFileStream {
         unsafe { rsgx_fopen(filename, mode, key).map(|f| SgxFileStream { stream: f }) }
     }
 
+    ///
+    /// The open_weak_key function creates or opens a protected file without checking the key.
+    ///
+    /// # Description
+    ///
+    /// open_weak_key behaves like open, except that it also accepts an all-zero key in modes that can write.
+    /// Such a file is only as confidential as its name. This is intended for tests and for recreating files
+    /// that older code wrote with an all-zero key; production code should use open.
+    ///
+    /// # Return value
+    ///
+    /// If the function succeeds, it returns a valid file pointer, which can be used by all the other functions
+    /// in the Protected FS API, otherwise, error code is returned.
+    ///
+    pub fn open_weak_key(filename: &CStr, mode: &CStr, key: &sgx_key_128bit_t) -> SysResult<SgxFileStream> {
+        unsafe { rsgx_fopen_weak_key(filename, mode, key).map(|f| SgxFileStream { stream: f }) }
+    }
+
     ///
     /// The open_auto_key function creates or opens a protected file.
     ///
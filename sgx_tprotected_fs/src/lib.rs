@@ -20,6 +20,13 @@
 //! Intel Protected File System Library provides protected files API for Intel SGX enclaves.
 //! With this API, the files are encrypted and saved on the untrusted disk during a write operation,
 //! and they are verified for confidentiality and integrity during a read operation.
+//!
+//! This crate is a thin binding over Intel's `libsgx_tprotected_fs.a`; `std::sgxfs` in
+//! sgx_tstd is built on top of it. Everything below the `sgx_f*` calls lives in that C
+//! library: key derivation from the user or sealing key, the metadata and MHT node layout,
+//! node encryption, the decrypted-node cache and the recovery file. None of it has a Rust
+//! type or an extension point here, so changes to the on-disk format, the key hierarchy or
+//! the caching policy have to be made in Intel's library rather than in this crate.
 
 #![no_std]
 #![cfg_attr(target_env = "sgx", feature(rustc_private))]
@@ -230,6 +230,324 @@ pub fn rsgx_get_align_key(key_request: &sgx_key_request_t) -> SgxResult<sgx_alig
     }
 }
 
+///
+/// The rsgx_self_report function returns a copy of the report of the current enclave.
+///
+/// # Description
+///
+/// The report is generated with EREPORT on the first call and cached by libsgx_tservice;
+/// later calls only copy the cached structure. Everything in it (measurements, attributes,
+/// SVNs, CONFIGID) is fixed when the enclave is initialized, so the cache never needs to be
+/// invalidated while the enclave is loaded.
+///
+/// # Requirements
+///
+/// Library: libsgx_tservice.a
+///
+/// # Return value
+///
+/// The report of the current enclave.
+///
 pub fn rsgx_self_report() -> sgx_report_t {
     unsafe { *sgx_self_report() }
 }
+
+///
+/// The rsgx_self_report_ref function returns a reference to the cached report of the current enclave.
+///
+/// # Description
+///
+/// Same as rsgx_self_report, without copying the 432-byte report. There is no call to
+/// invalidate the cached report: libsgx_tservice keeps it in a private static, and none of
+/// the fields can change while the enclave is loaded. A microcode update that raises CPUSVN
+/// takes effect only after the enclave has been destroyed and rebuilt.
+///
+/// # Requirements
+///
+/// Library: libsgx_tservice.a
+///
+/// # Return value
+///
+/// A reference to the report of the current enclave.
+///
+pub fn rsgx_self_report_ref() -> &'static sgx_report_t {
+    unsafe { &*sgx_self_report() }
+}
+
+///
+/// The rsgx_self_target function returns the target information of the current enclave.
+///
+/// # Description
+///
+/// In local attestation the verifying enclave sends this structure to its peer, which passes it
+/// to rsgx_create_report. Only the enclave described by the target information can then check
+/// the resulting report with rsgx_verify_report, so a key exchange can put its public values in
+/// report_data and bind them to both enclave identities.
+///
+/// # Requirements
+///
+/// Library: libsgx_tservice.a
+///
+/// # Return value
+///
+/// The target information of the current enclave.
+///
+/// # Errors
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// Indicates an unexpected error occurs.
+///
+pub fn rsgx_self_target() -> SgxResult<sgx_target_info_t> {
+    let mut target_info = sgx_target_info_t::default();
+    let ret = unsafe { sgx_self_target(&mut target_info as *mut sgx_target_info_t) };
+    match ret {
+        sgx_status_t::SGX_SUCCESS => Ok(target_info),
+        _ => Err(ret),
+    }
+}
+
+///
+/// The rsgx_self_svn function returns the CPUSVN and ISVSVN of the current enclave.
+///
+/// # Description
+///
+/// The values are read from the enclave's own report, as returned by rsgx_self_report.
+/// They are the highest SVNs that may be used in a key request from this enclave.
+///
+/// # Requirements
+///
+/// Library: libsgx_tservice.a
+///
+/// # Return value
+///
+/// The pair (cpu_svn, isv_svn).
+///
+pub fn rsgx_self_svn() -> (sgx_cpu_svn_t, sgx_isv_svn_t) {
+    let report = rsgx_self_report();
+    (report.body.cpu_svn, report.body.isv_svn)
+}
+
+const KEY_POLICY_KSS: uint16_t =
+    SGX_KEYPOLICY_CONFIGID | SGX_KEYPOLICY_ISVFAMILYID | SGX_KEYPOLICY_ISVEXTPRODID;
+
+const KEY_POLICY_MASK: uint16_t = SGX_KEYPOLICY_MRENCLAVE
+    | SGX_KEYPOLICY_MRSIGNER
+    | SGX_KEYPOLICY_NOISVPRODID
+    | KEY_POLICY_KSS;
+
+///
+/// Builder for sgx_key_request_t.
+///
+/// Fields that are not set take the same defaults as rsgx_seal_data: the key is bound to
+/// MRSIGNER (plus the KSS identity bits if the enclave has KSS enabled), the attribute and
+/// misc masks are TSEAL_DEFAULT_FLAGSMASK and TSEAL_DEFAULT_MISCMASK, and the SVNs are
+/// taken from the current enclave report. The key ID defaults to zero; seal keys should
+/// normally be given a random one.
+///
+/// ```ignore
+/// let key_request = SgxKeyRequestBuilder::new(SGX_KEYSELECT_SEAL).key_id(key_id).build()?;
+/// ```
+///
+#[derive(Clone, Copy, Default)]
+pub struct SgxKeyRequestBuilder {
+    key_name: uint16_t,
+    key_policy: Option<uint16_t>,
+    isv_svn: Option<sgx_isv_svn_t>,
+    cpu_svn: Option<sgx_cpu_svn_t>,
+    config_svn: Option<sgx_config_svn_t>,
+    attribute_mask: Option<sgx_attributes_t>,
+    key_id: sgx_key_id_t,
+    misc_mask: Option<sgx_misc_select_t>,
+    expected_mr_enclave: Option<sgx_measurement_t>,
+    expected_mr_signer: Option<sgx_measurement_t>,
+}
+
+impl SgxKeyRequestBuilder {
+    pub fn new(key_name: uint16_t) -> SgxKeyRequestBuilder {
+        SgxKeyRequestBuilder {
+            key_name,
+            ..Default::default()
+        }
+    }
+
+    /// A builder for the seal key (SGX_KEYSELECT_SEAL).
+    pub fn seal() -> SgxKeyRequestBuilder {
+        SgxKeyRequestBuilder::new(SGX_KEYSELECT_SEAL)
+    }
+
+    /// A builder for the report key (SGX_KEYSELECT_REPORT). EGETKEY ignores
+    /// the policy, SVNs and masks for this key.
+    pub fn report() -> SgxKeyRequestBuilder {
+        SgxKeyRequestBuilder::new(SGX_KEYSELECT_REPORT)
+    }
+
+    /// A builder for the provisioning key (SGX_KEYSELECT_PROVISION). Only
+    /// enclaves with the PROVISIONKEY attribute can request it.
+    ///
+    /// Unless key_policy is called, the policy is MRSIGNER alone, without the
+    /// KSS bits a seal key would get. A policy with MRENCLAVE makes build fail
+    /// with SGX_ERROR_INVALID_PARAMETER, and an expected MRENCLAVE or MRSIGNER
+    /// that does not match fails with SGX_ERROR_MAC_MISMATCH.
+    pub fn provision() -> SgxKeyRequestBuilder {
+        SgxKeyRequestBuilder::new(SGX_KEYSELECT_PROVISION)
+    }
+
+    /// A builder for the provisioning seal key (SGX_KEYSELECT_PROVISION_SEAL).
+    /// Only enclaves with the PROVISIONKEY attribute can request it. The key
+    /// policy is restricted the same way as for provision.
+    pub fn provision_seal() -> SgxKeyRequestBuilder {
+        SgxKeyRequestBuilder::new(SGX_KEYSELECT_PROVISION_SEAL)
+    }
+
+    pub fn key_name(mut self, key_name: uint16_t) -> Self {
+        self.key_name = key_name;
+        self
+    }
+
+    pub fn key_policy(mut self, key_policy: uint16_t) -> Self {
+        self.key_policy = Some(key_policy);
+        self
+    }
+
+    pub fn isv_svn(mut self, isv_svn: sgx_isv_svn_t) -> Self {
+        self.isv_svn = Some(isv_svn);
+        self
+    }
+
+    pub fn cpu_svn(mut self, cpu_svn: sgx_cpu_svn_t) -> Self {
+        self.cpu_svn = Some(cpu_svn);
+        self
+    }
+
+    pub fn config_svn(mut self, config_svn: sgx_config_svn_t) -> Self {
+        self.config_svn = Some(config_svn);
+        self
+    }
+
+    pub fn attribute_mask(mut self, attribute_mask: sgx_attributes_t) -> Self {
+        self.attribute_mask = Some(attribute_mask);
+        self
+    }
+
+    pub fn key_id(mut self, key_id: sgx_key_id_t) -> Self {
+        self.key_id = key_id;
+        self
+    }
+
+    pub fn misc_mask(mut self, misc_mask: sgx_misc_select_t) -> Self {
+        self.misc_mask = Some(misc_mask);
+        self
+    }
+
+    /// Makes build fail unless the current enclave has this MRENCLAVE.
+    pub fn expected_mr_enclave(mut self, mr_enclave: sgx_measurement_t) -> Self {
+        self.expected_mr_enclave = Some(mr_enclave);
+        self
+    }
+
+    /// Makes build fail unless the current enclave has this MRSIGNER.
+    pub fn expected_mr_signer(mut self, mr_signer: sgx_measurement_t) -> Self {
+        self.expected_mr_signer = Some(mr_signer);
+        self
+    }
+
+    ///
+    /// Builds the key request, filling unset fields with their defaults.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The key name is unknown, the key policy has unknown bits set, a seal key
+    /// policy is bound to neither MRENCLAVE nor MRSIGNER, a provisioning key policy
+    /// asks for MRENCLAVE, or the policy asks for CONFIGID, ISVFAMILYID or
+    /// ISVEXTPRODID while the enclave does not have KSS enabled.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// An expected MRENCLAVE or MRSIGNER was set and does not match the current enclave.
+    /// This is checked against the enclave's own report, so a key request taken from
+    /// tampered metadata is rejected before it reaches EGETKEY. It is the status unsealing
+    /// would have failed with, since the key would belong to a different enclave.
+    ///
+    /// **SGX_ERROR_INVALID_ATTRIBUTE**
+    ///
+    /// A provisioning or provisioning seal key was requested and the enclave does not
+    /// have the PROVISIONKEY attribute.
+    ///
+    pub fn build(&self) -> SgxResult<sgx_key_request_t> {
+        if self.key_name > SGX_KEYSELECT_SEAL {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        // Provisioning keys are always derived from MRSIGNER.
+        let provision = self.key_name == SGX_KEYSELECT_PROVISION
+            || self.key_name == SGX_KEYSELECT_PROVISION_SEAL;
+        let needs_report = self.key_policy.map_or(true, |p| (p & KEY_POLICY_KSS) != 0)
+            || provision
+            || self.isv_svn.is_none()
+            || self.cpu_svn.is_none()
+            || self.config_svn.is_none()
+            || self.expected_mr_enclave.is_some()
+            || self.expected_mr_signer.is_some();
+        let report = if needs_report {
+            rsgx_self_report()
+        } else {
+            sgx_report_t::default()
+        };
+
+        let key_policy = match self.key_policy {
+            Some(key_policy) => key_policy,
+            None if provision => SGX_KEYPOLICY_MRSIGNER,
+            None if (report.body.attributes.flags & SGX_FLAGS_KSS) != 0 => {
+                SGX_KEYPOLICY_MRSIGNER | KEY_POLICY_KSS
+            }
+            None => SGX_KEYPOLICY_MRSIGNER,
+        };
+        if (key_policy & !KEY_POLICY_MASK) != 0 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        if self.key_name == SGX_KEYSELECT_SEAL
+            && (key_policy & (SGX_KEYPOLICY_MRENCLAVE | SGX_KEYPOLICY_MRSIGNER)) == 0
+        {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        if provision && (key_policy & SGX_KEYPOLICY_MRENCLAVE) != 0 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        if (key_policy & KEY_POLICY_KSS) != 0
+            && (report.body.attributes.flags & SGX_FLAGS_KSS) == 0
+        {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        if self
+            .expected_mr_enclave
+            .map_or(false, |mr| mr.m != report.body.mr_enclave.m)
+            || self
+                .expected_mr_signer
+                .map_or(false, |mr| mr.m != report.body.mr_signer.m)
+        {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        if provision && (report.body.attributes.flags & SGX_FLAGS_PROVISION_KEY) == 0 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_ATTRIBUTE);
+        }
+
+        Ok(sgx_key_request_t {
+            key_name: self.key_name,
+            key_policy,
+            isv_svn: self.isv_svn.unwrap_or(report.body.isv_svn),
+            reserved1: 0_u16,
+            cpu_svn: self.cpu_svn.unwrap_or(report.body.cpu_svn),
+            attribute_mask: self.attribute_mask.unwrap_or(sgx_attributes_t {
+                flags: TSEAL_DEFAULT_FLAGSMASK,
+                xfrm: 0,
+            }),
+            key_id: self.key_id,
+            misc_mask: self.misc_mask.unwrap_or(TSEAL_DEFAULT_MISCMASK),
+            config_svn: self.config_svn.unwrap_or(report.body.config_svn),
+            reserved2: [0_u8; SGX_KEY_REQUEST_RESERVED2_BYTES],
+        })
+    }
+}
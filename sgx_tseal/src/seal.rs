@@ -30,11 +30,29 @@
 use crate::internal::*;
 use alloc::boxed::Box;
 use alloc::slice;
+use alloc::vec::Vec;
 use core::marker::PhantomData;
 use core::mem;
+use sgx_tcrypto::rsgx_ct_compare;
 use sgx_types::marker::ContiguousMemory;
 use sgx_types::*;
 
+// Largest AAD accepted by seal_data_with_aad and unseal_data_with_aad.
+const SEAL_MAX_AAD_SIZE: usize = 64 * 1024;
+
+// The additional text seal_data_with_aad stores: the AAD length as a little-endian
+// u32, then the AAD. The prefix keeps these blobs apart from seal_data blobs whose
+// additional text merely happens to equal some AAD.
+fn aad_text(aad: &[u8]) -> SgxResult<Vec<u8>> {
+    if aad.len() > SEAL_MAX_AAD_SIZE {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let mut text = Vec::with_capacity(mem::size_of::<u32>() + aad.len());
+    text.extend_from_slice(&(aad.len() as u32).to_le_bytes());
+    text.extend_from_slice(aad);
+    Ok(text)
+}
+
 /// The structure about the unsealed data.
 pub struct SgxUnsealedData<'a, T: 'a + ?Sized> {
     pub payload_size: u32,
@@ -370,6 +388,77 @@ impl<'a, T: 'a + Copy + ContiguousMemory> SgxSealedData<'a, T> {
         })
     }
 
+    ///
+    /// Seal the data and bind `aad` to it, for use with unseal_data_with_aad.
+    ///
+    /// # Description
+    ///
+    /// The AAD is stored, prefixed with its length as a little-endian u32, as the
+    /// additional text of seal_data, so it is covered by the GCM MAC but not
+    /// encrypted. Use it for the context the blob belongs to, such as a version
+    /// number or a file path. get_additional_txt returns the prefixed form.
+    ///
+    /// # Requirements
+    ///
+    /// Library: libsgx_tservice.a or libsgx_tservice_sim.a (simulation)
+    ///
+    /// # Return value
+    ///
+    /// The sealed data in SgxSealedData.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// `aad` is longer than 64 KiB (this is the EINVAL of the sealing API), or
+    /// seal_data failed with this error.
+    ///
+    /// Other errors are the same as for seal_data.
+    ///
+    pub fn seal_data_with_aad(aad: &[u8], encrypt_text: &'a T) -> SgxResult<Self> {
+        let additional_text = aad_text(aad)?;
+        Self::seal_data(&additional_text, encrypt_text)
+    }
+
+    ///
+    /// Unseal data sealed by seal_data_with_aad and check that it was bound to `aad`.
+    ///
+    /// # Description
+    ///
+    /// The GCM MAC already prevents the additional text from being modified. This
+    /// function also rejects a valid blob that was sealed for another context. The
+    /// comparison is constant time. Blobs from seal_data do not carry the length
+    /// prefix and are rejected.
+    ///
+    /// # Requirements
+    ///
+    /// Library: libsgx_tservice.a or libsgx_tservice_sim.a (simulation)
+    ///
+    /// # Return value
+    ///
+    /// The unsealed data in SgxUnsealedData.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// `aad` is longer than 64 KiB, or unseal_data failed with this error.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The blob was bound to a different AAD, or unseal_data failed with this error.
+    ///
+    /// Other errors are the same as for unseal_data.
+    ///
+    pub fn unseal_data_with_aad(&self, aad: &[u8]) -> SgxResult<SgxUnsealedData<'a, T>> {
+        let expected = aad_text(aad)?;
+        let unsealed = self.unseal_data()?;
+        if !rsgx_ct_compare(unsealed.get_additional_txt(), &expected) {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        Ok(unsealed)
+    }
+
     ///
     /// Convert a pointer of sgx_sealed_data_t buffer to SgxSealedData.
     ///
@@ -691,6 +780,77 @@ impl<'a, T: 'a + Copy + ContiguousMemory> SgxSealedData<'a, [T]> {
         })
     }
 
+    ///
+    /// Seal the data and bind `aad` to it, for use with unseal_data_with_aad.
+    ///
+    /// # Description
+    ///
+    /// The AAD is stored, prefixed with its length as a little-endian u32, as the
+    /// additional text of seal_data, so it is covered by the GCM MAC but not
+    /// encrypted. Use it for the context the blob belongs to, such as a version
+    /// number or a file path. get_additional_txt returns the prefixed form.
+    ///
+    /// # Requirements
+    ///
+    /// Library: libsgx_tservice.a or libsgx_tservice_sim.a (simulation)
+    ///
+    /// # Return value
+    ///
+    /// The sealed data in SgxSealedData.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// `aad` is longer than 64 KiB (this is the EINVAL of the sealing API), or
+    /// seal_data failed with this error.
+    ///
+    /// Other errors are the same as for seal_data.
+    ///
+    pub fn seal_data_with_aad(aad: &[u8], encrypt_text: &'a [T]) -> SgxResult<Self> {
+        let additional_text = aad_text(aad)?;
+        Self::seal_data(&additional_text, encrypt_text)
+    }
+
+    ///
+    /// Unseal data sealed by seal_data_with_aad and check that it was bound to `aad`.
+    ///
+    /// # Description
+    ///
+    /// The GCM MAC already prevents the additional text from being modified. This
+    /// function also rejects a valid blob that was sealed for another context. The
+    /// comparison is constant time. Blobs from seal_data do not carry the length
+    /// prefix and are rejected.
+    ///
+    /// # Requirements
+    ///
+    /// Library: libsgx_tservice.a or libsgx_tservice_sim.a (simulation)
+    ///
+    /// # Return value
+    ///
+    /// The unsealed data in SgxUnsealedData.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// `aad` is longer than 64 KiB, or unseal_data failed with this error.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The blob was bound to a different AAD, or unseal_data failed with this error.
+    ///
+    /// Other errors are the same as for unseal_data.
+    ///
+    pub fn unseal_data_with_aad(&self, aad: &[u8]) -> SgxResult<SgxUnsealedData<'a, [T]>> {
+        let expected = aad_text(aad)?;
+        let unsealed = self.unseal_data()?;
+        if !rsgx_ct_compare(unsealed.get_additional_txt(), &expected) {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        Ok(unsealed)
+    }
+
     ///
     /// Convert a pointer of sgx_sealed_data_t buffer to SgxSealedData.
     ///
@@ -22,6 +22,7 @@ use crate::path::Path;
 use crate::sys::sgxfs as fs_imp;
 use crate::sys_common::{AsInner, AsInnerMut, FromInner, IntoInner};
 use sgx_types::{sgx_key_128bit_t, sgx_align_key_128bit_t};
+use sgx_types::{sgx_attributes_t, sgx_cpu_svn_t, sgx_isv_svn_t, sgx_key_id_t};
 
 /// A reference to an open file on the filesystem.
 ///
@@ -32,6 +33,7 @@ use sgx_types::{sgx_key_128bit_t, sgx_align_key_128bit_t};
 /// Files are automatically closed when they go out of scope.
 pub struct SgxFile {
     inner: fs_imp::SgxFile,
+    write_through: bool,
 }
 
 /// Options and flags which can be used to configure how a file is opened.
@@ -103,7 +105,10 @@ impl SgxFile {
     /// # Errors
     ///
     /// This function will return an error if `path` does not already exist.
-    /// Other errors may also be returned according to [`OpenOptions::open`].
+    /// If the file was written in a format version this SDK does not support,
+    /// an error of kind [`io::ErrorKind::InvalidData`] naming the found and
+    /// supported versions is returned. Other errors may also be returned
+    /// according to [`OpenOptions::open`].
     ///
     pub fn open<P: AsRef<Path>>(path: P) -> io::Result<SgxFile> {
         OpenOptions::new().read(true).open(path.as_ref())
@@ -118,6 +123,15 @@ impl SgxFile {
         OpenOptions::new().write(true).open(path.as_ref())
     }
 
+    /// Attempts to open a file protected with a user key in read-only mode.
+    ///
+    /// # Errors
+    ///
+    /// If the file was created with an automatic key (see [`SgxFile::create`]),
+    /// an error of kind [`io::ErrorKind::InvalidInput`] is returned instead of
+    /// the MAC failure the protected file library reports; [`SgxFile::open`]
+    /// does the same for files created with a user key.
+    ///
     pub fn open_ex<P: AsRef<Path>>(path: P, key: &sgx_key_128bit_t) -> io::Result<SgxFile> {
         OpenOptions::new().read(true).open_ex(path.as_ref(), key)
     }
@@ -126,6 +140,15 @@ impl SgxFile {
         OpenOptions::new().write(true).open_ex(path.as_ref(), key)
     }
 
+    /// Reads the unencrypted header of the protected file at `path` without
+    /// opening it, so no key is derived. See [`SgxFileHeader::read`].
+    ///
+    /// The logical length lives in the encrypted part of the metadata and is
+    /// only available through [`SgxFile::logical_len`] on an open file.
+    pub fn read_header<P: AsRef<Path>>(path: P) -> io::Result<SgxFileHeader> {
+        SgxFileHeader::read(path)
+    }
+
     pub fn is_eof(&self) -> bool {
         self.inner.is_eof()
     }
@@ -137,6 +160,70 @@ impl SgxFile {
     pub fn clear_cache(&self) -> io::Result<()> {
         self.inner.clear_cache()
     }
+
+    /// Returns the logical (plaintext) length of the file, including writes
+    /// that have not been flushed yet.
+    ///
+    /// The length is kept in the encrypted metadata, so no data node is read.
+    /// The file position is left unchanged.
+    pub fn logical_len(&self) -> io::Result<u64> {
+        self.inner.logical_len()
+    }
+
+    /// Returns the number of 4 KiB nodes (metadata, MHT and data nodes) the
+    /// file occupies on disk once flushed.
+    ///
+    /// This is computed from [`logical_len`] and the node layout of the
+    /// protected file library, so it is also a cheap estimate of the cost of
+    /// a [`validate`] sweep.
+    ///
+    /// [`logical_len`]: SgxFile::logical_len
+    /// [`validate`]: SgxFile::validate
+    pub fn node_count(&self) -> io::Result<u64> {
+        self.inner.node_count()
+    }
+
+    /// Verifies the integrity of the whole file.
+    ///
+    /// Every node is loaded from disk and its MAC checked by the protected
+    /// file library; the decrypted data goes to an internal scratch buffer
+    /// that is wiped afterwards and nothing is returned to the caller.
+    /// Cached nodes are dropped first so that they are checked against the
+    /// on-disk copy as well. The file position is restored on return.
+    ///
+    /// Nothing is written to the file. Dropping the cache of a handle that
+    /// can write would first write its dirty nodes back, so the file must have
+    /// been opened read-only (`r` without `+`); other handles get an error of
+    /// kind [`io::ErrorKind::InvalidInput`]. The file is read one node at a
+    /// time, and a failure names the physical node number and the logical
+    /// offset that was being loaded, e.g. `protected file node 99 (offset
+    /// 396288) failed to load: ...`. The failing MAC may belong to that node
+    /// or to an MHT node above it.
+    pub fn validate(&mut self) -> io::Result<()> {
+        self.inner.validate()
+    }
+
+    /// Writes the decrypted contents of the whole file to `sink` and returns
+    /// the number of bytes written.
+    ///
+    /// The file is read once, one node at a time, and each node's MAC is
+    /// checked by the protected file library before its plaintext is passed
+    /// to `sink`. If a node fails, the error names it as for
+    /// [`validate`](SgxFile::validate) and `sink` has received only the
+    /// verified data in front of it, which the caller must discard. Nodes
+    /// already in the cache, including unflushed writes of this handle, are
+    /// exported as cached. The file position is restored on return.
+    pub fn export_to<W: Write + ?Sized>(&mut self, sink: &mut W) -> io::Result<u64> {
+        self.inner.export_to(sink)
+    }
+
+    /// Turns write-through caching on or off for this handle.
+    ///
+    /// See [`OpenOptions::write_through`]. This allows switching it on only
+    /// around the writes that must be durable as soon as they return.
+    pub fn set_write_through(&mut self, write_through: bool) {
+        self.write_through = write_through;
+    }
 }
 
 impl AsInner<fs_imp::SgxFile> for SgxFile {
@@ -144,13 +231,11 @@ impl AsInner<fs_imp::SgxFile> for SgxFile {
 }
 impl FromInner<fs_imp::SgxFile> for SgxFile {
     fn from_inner(f: fs_imp::SgxFile) -> SgxFile {
-        SgxFile { inner: f }
+        SgxFile { inner: f, write_through: false }
     }
 }
 impl IntoInner<fs_imp::SgxFile> for SgxFile {
-    fn into_inner(self) -> fs_imp::SgxFile {
-        self.inner
-    }
+    fn into_inner(self) -> fs_imp::SgxFile { self.inner }
 }
 
 impl Read for SgxFile {
@@ -161,7 +246,7 @@ impl Read for SgxFile {
 
 impl Write for SgxFile {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.inner.write(buf)
+        (&*self).write(buf)
     }
     fn flush(&mut self) -> io::Result<()> { self.inner.flush() }
 }
@@ -180,7 +265,11 @@ impl<'a> Read for &'a SgxFile {
 
 impl<'a> Write for &'a SgxFile {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.inner.write(buf)
+        let n = self.inner.write(buf)?;
+        if self.write_through {
+            self.inner.flush()?;
+        }
+        Ok(n)
     }
     fn flush(&mut self) -> io::Result<()> { self.inner.flush() }
 }
@@ -254,6 +343,56 @@ impl OpenOptions {
         self.0.binary(binary); self
     }
 
+    /// Sets a function to call if flushing the file fails when it is dropped.
+    ///
+    /// Dropping an `SgxFile` closes it, which writes back any dirty nodes,
+    /// but `drop` cannot return the error. With a hook set, the file is
+    /// flushed explicitly before it is closed and `hook` receives the error
+    /// if that fails. Calling [`flush`] on the file before dropping it is
+    /// still the recommended way to make sure the data is on disk.
+    ///
+    /// [`flush`]: Write::flush
+    pub fn on_drop_error(&mut self, hook: fn(&io::Error)) -> &mut OpenOptions {
+        self.0.drop_error_hook(hook); self
+    }
+
+    /// Sets the option for write-through caching.
+    ///
+    /// By default writes stay in the enclave's node cache until the file is
+    /// flushed or closed. With this option set, every successful write is
+    /// followed by a flush, so the data and the updated MACs are on disk
+    /// when the write returns. This costs one re-encryption of each touched
+    /// node per write; it does not change the resulting file format.
+    pub fn write_through(&mut self, write_through: bool) -> &mut OpenOptions {
+        self.0.write_through(write_through); self
+    }
+
+    /// Sets the option for verifying the whole file when it is opened.
+    ///
+    /// When true and the file is opened with read access, every node is
+    /// checked as by [`SgxFile::validate`] before the handle is returned. If
+    /// any node fails, `open` returns the error and no handle, so a caller
+    /// can't act on the beginning of a file whose later nodes were tampered
+    /// with. Opening costs a full read of the file. The check covers the file
+    /// as it is at open time; it does not protect against modification of
+    /// the host file while it is open, which later reads still detect.
+    pub fn verify_on_open(&mut self, verify_on_open: bool) -> &mut OpenOptions {
+        self.0.verify_on_open(verify_on_open); self
+    }
+
+    /// Sets the option for accepting an all-zero key in [`open_ex`].
+    ///
+    /// By default, opening a file for writing, appending or updating with
+    /// an all-zero key fails with `EINVAL`, whether or not the file exists,
+    /// because every key derived from it is public. Opening an existing
+    /// file read-only is always allowed. Set this only in tests, or to
+    /// modify a file that older code wrote with an all-zero key.
+    ///
+    /// [`open_ex`]: OpenOptions::open_ex
+    pub fn allow_weak_key(&mut self, allow_weak_key: bool) -> &mut OpenOptions {
+        self.0.allow_weak_key(allow_weak_key); self
+    }
+
     /// Opens a file at `path` with the options specified by `self`.
     pub fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<SgxFile> {
         self._open(path.as_ref())
@@ -265,12 +404,19 @@ impl OpenOptions {
 
     fn _open(&self, path: &Path) -> io::Result<SgxFile> {
         let inner = fs_imp::SgxFile::open(path, &self.0)?;
-        Ok(SgxFile { inner })
+        Ok(self.wrap(inner))
     }
 
     fn _open_ex(&self, path: &Path, key: &sgx_key_128bit_t) -> io::Result<SgxFile> {
         let inner = fs_imp::SgxFile::open_ex(path, &self.0, key)?;
-        Ok(SgxFile { inner })
+        Ok(self.wrap(inner))
+    }
+
+    fn wrap(&self, inner: fs_imp::SgxFile) -> SgxFile {
+        SgxFile {
+            inner,
+            write_through: self.0.get_write_through(),
+        }
     }
 }
 
@@ -294,6 +440,78 @@ pub fn import_auto_key<P: AsRef<Path>>(path: P, key: &sgx_key_128bit_t) -> io::R
     fs_imp::import_auto_key(path.as_ref(), key)
 }
 
+/// The unencrypted part of a protected file's metadata node.
+///
+/// This part is stored in the clear at the start of the file. It holds the
+/// parameters the protected file library uses to derive the metadata key,
+/// but not the key itself. Changing any of it makes the file fail to open.
+///
+/// The header is read directly from the file on the host, so the values are
+/// untrusted until the file has been opened successfully.
+#[derive(Clone, Copy)]
+pub struct SgxFileHeader(fs_imp::FileHeader);
+
+impl SgxFileHeader {
+    /// Reads the header of the protected file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind InvalidData if the file does not start with
+    /// a protected file header of a supported version.
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<SgxFileHeader> {
+        fs_imp::read_header(path.as_ref()).map(SgxFileHeader)
+    }
+
+    /// The (major, minor) format version of the file.
+    pub fn version(&self) -> (u8, u8) {
+        (self.0.major_version, self.0.minor_version)
+    }
+
+    /// The key ID the metadata key was derived with.
+    ///
+    /// For files using an automatic key this is the key_id of the seal key
+    /// request. For files using a user key it is the KDF nonce.
+    ///
+    /// An open [`SgxFile`] has no accessor for it. The protected file library
+    /// keeps the metadata node it decrypted inside its file object, and none
+    /// of the `sgx_f*` calls hands it out. Such an accessor could only re-read
+    /// this same untrusted header, and the host may have replaced the file
+    /// since it was opened.
+    pub fn key_id(&self) -> &sgx_key_id_t {
+        &self.0.key_id
+    }
+
+    /// The CPUSVN of the seal key request. Only meaningful if
+    /// `is_user_key` is false.
+    pub fn cpu_svn(&self) -> &sgx_cpu_svn_t {
+        &self.0.cpu_svn
+    }
+
+    /// The ISVSVN of the seal key request. Only meaningful if
+    /// `is_user_key` is false.
+    pub fn isv_svn(&self) -> sgx_isv_svn_t {
+        self.0.isv_svn
+    }
+
+    /// The attribute mask of the seal key request. Only meaningful if
+    /// `is_user_key` is false.
+    pub fn attribute_mask(&self) -> &sgx_attributes_t {
+        &self.0.attribute_mask
+    }
+
+    /// Whether the file was created with a user-supplied key rather than
+    /// an automatic (seal) key.
+    pub fn is_user_key(&self) -> bool {
+        self.0.use_user_kdk_key
+    }
+
+    /// Whether the last update of the file was interrupted before it was
+    /// committed. Such a file needs its recovery file to be opened again.
+    pub fn is_update_pending(&self) -> bool {
+        self.0.update_flag
+    }
+}
+
 /// Copies the contents of one file to another.
 /// This function will **overwrite** the contents of `to`.
 ///
@@ -17,14 +17,19 @@
 
 use crate::os::unix::prelude::*;
 use crate::ffi::{CString, CStr};
-use crate::io::{self, Error, SeekFrom};
+use crate::io::{self, Error, Read, SeekFrom, Write};
 use crate::path::Path;
 use crate::sys_common::FromInner;
 use sgx_libc as libc;
 use sgx_tprotected_fs::{self, SgxFileStream};
-use sgx_types::{sgx_status_t, sgx_key_128bit_t, sgx_align_key_128bit_t};
+use sgx_types::{SysResult, sgx_status_t, sgx_key_128bit_t, sgx_align_key_128bit_t};
+use sgx_types::{sgx_attributes_t, sgx_cpu_svn_t, sgx_isv_svn_t, sgx_key_id_t};
 
-pub struct SgxFile(SgxFileStream);
+pub struct SgxFile {
+    stream: SgxFileStream,
+    drop_error_hook: Option<fn(&io::Error)>,
+    writable: bool,
+}
 
 #[derive(Clone, Debug)]
 pub struct OpenOptions {
@@ -33,6 +38,10 @@ pub struct OpenOptions {
     append: bool,
     update: bool,
     binary: bool,
+    drop_error_hook: Option<fn(&io::Error)>,
+    write_through: bool,
+    verify_on_open: bool,
+    allow_weak_key: bool,
 }
 
 impl OpenOptions {
@@ -43,6 +52,10 @@ impl OpenOptions {
             append: false,
             update: false,
             binary: false,
+            drop_error_hook: None,
+            write_through: false,
+            verify_on_open: false,
+            allow_weak_key: false,
         }
     }
 
@@ -61,6 +74,30 @@ impl OpenOptions {
     pub fn binary(&mut self, binary: bool) {
         self.binary = binary;
     }
+    pub fn drop_error_hook(&mut self, hook: fn(&io::Error)) {
+        self.drop_error_hook = Some(hook);
+    }
+    pub fn get_drop_error_hook(&self) -> Option<fn(&io::Error)> {
+        self.drop_error_hook
+    }
+    pub fn write_through(&mut self, write_through: bool) {
+        self.write_through = write_through;
+    }
+    pub fn get_write_through(&self) -> bool {
+        self.write_through
+    }
+    pub fn verify_on_open(&mut self, verify_on_open: bool) {
+        self.verify_on_open = verify_on_open;
+    }
+    pub fn get_verify_on_open(&self) -> bool {
+        self.verify_on_open
+    }
+    pub fn allow_weak_key(&mut self, allow_weak_key: bool) {
+        self.allow_weak_key = allow_weak_key;
+    }
+    pub fn get_allow_weak_key(&self) -> bool {
+        self.allow_weak_key
+    }
 
     fn get_access_mode(&self) -> io::Result<String> {
         let mut mode = match (self.read, self.write, self.append) {
@@ -81,17 +118,25 @@ impl OpenOptions {
 
 impl SgxFile {
     pub fn open(path: &Path, opts: &OpenOptions) -> io::Result<SgxFile> {
-        let path = cstr(path)?;
+        let c_path = cstr(path)?;
         let mode = opts.get_access_mode()?;
-        let opts = CString::new(mode.as_bytes())?;
-        SgxFile::open_c(&path, &opts, &sgx_key_128bit_t::default(), true)
+        let c_opts = CString::new(mode.as_bytes())?;
+        SgxFile::open_c(&c_path, &c_opts, &sgx_key_128bit_t::default(), true)
+            .map_err(|err| open_error(path, opts, false).unwrap_or(err))
+            .and_then(|file| finish_open(file, opts))
     }
 
     pub fn open_ex(path: &Path, opts: &OpenOptions, key: &sgx_key_128bit_t) -> io::Result<SgxFile> {
-        let path = cstr(path)?;
+        let c_path = cstr(path)?;
         let mode = opts.get_access_mode()?;
-        let opts = CString::new(mode.as_bytes())?;
-        SgxFile::open_c(&path, &opts, key, false)
+        let c_opts = CString::new(mode.as_bytes())?;
+        let file = if opts.allow_weak_key {
+            SgxFile::from_stream(SgxFileStream::open_weak_key(&c_path, &c_opts, key))
+        } else {
+            SgxFile::open_c(&c_path, &c_opts, key, false)
+        };
+        file.map_err(|err| open_error(path, opts, true).unwrap_or(err))
+            .and_then(|file| finish_open(file, opts))
     }
 
     pub fn open_c(path: &CStr, opts: &CStr, key: &sgx_key_128bit_t, auto: bool) -> io::Result<SgxFile> {
@@ -100,8 +145,11 @@ impl SgxFile {
         } else {
             SgxFileStream::open(path, opts, key)
         };
+        SgxFile::from_stream(file)
+    }
 
-        file.map(SgxFile)
+    fn from_stream(file: SysResult<SgxFileStream>) -> io::Result<SgxFile> {
+        file.map(|stream| SgxFile { stream, drop_error_hook: None, writable: true })
             .map_err(|err| {
                 match err {
                     1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
@@ -118,7 +166,7 @@ impl SgxFile {
     }
 
     pub fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
-        self.0.read(buf).map_err(|err| {
+        self.stream.read(buf).map_err(|err| {
             match err {
                 1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
                 2 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
@@ -134,7 +182,7 @@ impl SgxFile {
     }
 
     pub fn write(&self, buf: &[u8]) -> io::Result<usize> {
-        self.0.write(buf).map_err(|err| {
+        self.stream.write(buf).map_err(|err| {
             match err {
                 1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
                 2 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
@@ -150,7 +198,7 @@ impl SgxFile {
     }
 
     pub fn tell(&self) -> io::Result<u64> {
-        self.0.tell().map_err(|err| {
+        self.stream.tell().map_err(|err| {
             match err {
                 r if r > 4096 => {
                     let status = sgx_status_t::from_repr(r as u32).unwrap_or(sgx_status_t::SGX_ERROR_UNEXPECTED);
@@ -169,7 +217,7 @@ impl SgxFile {
             SeekFrom::Current(off) => (sgx_tprotected_fs::SeekFrom::Current, off),
         };
 
-        self.0.seek(offset, whence).map_err(|err| {
+        self.stream.seek(offset, whence).map_err(|err| {
             match err {
                 r if r > 4096 => {
                     let status = sgx_status_t::from_repr(r as u32).unwrap_or(sgx_status_t::SGX_ERROR_UNEXPECTED);
@@ -184,7 +232,7 @@ impl SgxFile {
     }
 
     pub fn flush(&self) -> io::Result<()> {
-        self.0.flush().map_err(|err| {
+        self.stream.flush().map_err(|err| {
             match err {
                 1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
                 2 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
@@ -200,15 +248,84 @@ impl SgxFile {
     }
 
     pub fn is_eof(&self) -> bool {
-        self.0.is_eof()
+        self.stream.is_eof()
     }
 
     pub fn clearerr(&self) {
-        self.0.clearerr()
+        self.stream.clearerr()
+    }
+
+    pub fn logical_len(&self) -> io::Result<u64> {
+        let pos = self.tell()?;
+        let len = self.seek(SeekFrom::End(0))?;
+        self.seek(SeekFrom::Start(pos))?;
+        Ok(len)
+    }
+
+    pub fn node_count(&self) -> io::Result<u64> {
+        self.logical_len().map(node_count)
+    }
+
+    pub fn validate(&self) -> io::Result<()> {
+        // sgx_fclear_cache writes dirty nodes back before dropping them, so the
+        // cache is only cleared on a handle that cannot have any.
+        if self.writable {
+            return Err(io::const_io_error!(
+                io::ErrorKind::InvalidInput,
+                "validate needs a protected file opened read-only",
+            ));
+        }
+        let pos = self.tell()?;
+        self.clear_cache()?;
+        let result = self.sweep(|_| Ok(()));
+        self.restore_position(pos, result).map(|_| ())
+    }
+
+    // Reads the file from the start, one node per read so that a failure can be
+    // attributed to the node being loaded, and hands each chunk to `f`. Returns the
+    // number of bytes read. The position is left wherever the sweep stopped.
+    fn sweep<F: FnMut(&[u8]) -> io::Result<()>>(&self, mut f: F) -> io::Result<u64> {
+        self.seek(SeekFrom::Start(0))?;
+
+        let mut buf = [0_u8; NODE_SIZE as usize];
+        let mut offset = 0_u64;
+        let result = loop {
+            let len = if offset < MD_USER_DATA_SIZE {
+                (MD_USER_DATA_SIZE - offset) as usize
+            } else {
+                NODE_SIZE as usize
+            };
+            match self.read(&mut buf[..len]) {
+                Ok(0) => break Ok(offset),
+                Ok(n) => match f(&buf[..n]) {
+                    Ok(()) => offset += n as u64,
+                    Err(e) => break Err(e),
+                },
+                Err(e) => break Err(node_error(offset, e)),
+            }
+        };
+        buf.iter_mut().for_each(|b| *b = 0);
+        result
+    }
+
+    pub fn export_to<W: Write + ?Sized>(&self, sink: &mut W) -> io::Result<u64> {
+        let pos = self.tell()?;
+        let result = self.sweep(|chunk| sink.write_all(chunk));
+        self.restore_position(pos, result)
+    }
+
+    // A node that fails its MAC check marks the file as corrupted, after which the
+    // seek back fails too. The sweep's error is the one worth reporting.
+    fn restore_position(&self, pos: u64, result: io::Result<u64>) -> io::Result<u64> {
+        self.clearerr();
+        let restored = self.seek(SeekFrom::Start(pos));
+        let len = result?;
+        restored?;
+        Ok(len)
     }
 
     pub fn clear_cache(&self) -> io::Result<()> {
-        self.0.clear_cache().map_err(|err| {
+        self.stream.clear_cache().map_err(|err| {
             match err {
                 1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
                 2 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
@@ -292,13 +409,183 @@ pub fn import_auto_key(path: &Path, key: &sgx_key_128bit_t) -> io::Result<()> {
     })
 }
 
+// Node layout of libsgx_tprotected_fs: the metadata node carries the first
+// MD_USER_DATA_SIZE bytes, then every MHT node is followed by the
+// ATTACHED_DATA_NODES_COUNT data nodes it covers.
+const NODE_SIZE: u64 = 4096;
+const MD_USER_DATA_SIZE: u64 = 3072;
+const ATTACHED_DATA_NODES_COUNT: u64 = 96;
+
+// Physical number of the node holding logical `offset`: data node d follows the
+// metadata node and the d / ATTACHED_DATA_NODES_COUNT + 1 MHT nodes in front of it.
+fn node_number(offset: u64) -> u64 {
+    if offset < MD_USER_DATA_SIZE {
+        return 0;
+    }
+    let data_node = (offset - MD_USER_DATA_SIZE) / NODE_SIZE;
+    2 + data_node + data_node / ATTACHED_DATA_NODES_COUNT
+}
+
+fn node_error(offset: u64, err: Error) -> Error {
+    Error::new(
+        err.kind(),
+        format!(
+            "protected file node {} (offset {}) failed to load: {}",
+            node_number(offset), offset, err,
+        ),
+    )
+}
+
+fn node_count(len: u64) -> u64 {
+    if len <= MD_USER_DATA_SIZE {
+        return 1;
+    }
+    let data_nodes = (len - MD_USER_DATA_SIZE + NODE_SIZE - 1) / NODE_SIZE;
+    let mht_nodes = (data_nodes + ATTACHED_DATA_NODES_COUNT - 1) / ATTACHED_DATA_NODES_COUNT;
+    1 + mht_nodes + data_nodes
+}
+
+// On-disk layout of meta_data_plain_t in libsgx_tprotected_fs (packed).
+const SGX_FILE_ID: u64 = 0x5347_585F_4649_4C45;
+const SGX_FILE_MAJOR_VERSION: u8 = 0x01;
+const META_DATA_PLAIN_SIZE: usize = 94;
+
+#[derive(Clone, Copy)]
+pub struct FileHeader {
+    pub major_version: u8,
+    pub minor_version: u8,
+    pub key_id: sgx_key_id_t,
+    pub cpu_svn: sgx_cpu_svn_t,
+    pub isv_svn: sgx_isv_svn_t,
+    pub use_user_kdk_key: bool,
+    pub attribute_mask: sgx_attributes_t,
+    pub update_flag: bool,
+}
+
+impl FileHeader {
+    fn parse(buf: &[u8; META_DATA_PLAIN_SIZE]) -> io::Result<FileHeader> {
+        let u64_at = |off: usize| {
+            let mut v = [0_u8; 8];
+            v.copy_from_slice(&buf[off..off + 8]);
+            u64::from_le_bytes(v)
+        };
+
+        if u64_at(0) != SGX_FILE_ID {
+            return Err(io::const_io_error!(
+                io::ErrorKind::InvalidData,
+                "not a protected file",
+            ));
+        }
+        let mut header = FileHeader {
+            major_version: buf[8],
+            minor_version: buf[9],
+            key_id: sgx_key_id_t::default(),
+            cpu_svn: sgx_cpu_svn_t::default(),
+            isv_svn: u16::from_le_bytes([buf[58], buf[59]]),
+            use_user_kdk_key: buf[60] != 0,
+            attribute_mask: sgx_attributes_t {
+                flags: u64_at(61),
+                xfrm: u64_at(69),
+            },
+            update_flag: buf[93] != 0,
+        };
+        header.key_id.id.copy_from_slice(&buf[10..42]);
+        header.cpu_svn.svn.copy_from_slice(&buf[42..58]);
+        Ok(header)
+    }
+
+    fn check_version(&self) -> io::Result<()> {
+        if self.major_version != SGX_FILE_MAJOR_VERSION {
+            return Err(Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "unsupported protected file format version {}.{}, supported major version is {}",
+                    self.major_version, self.minor_version, SGX_FILE_MAJOR_VERSION,
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub fn read_header(path: &Path) -> io::Result<FileHeader> {
+    let header = read_header_unchecked(path)?;
+    header.check_version()?;
+    Ok(header)
+}
+
+fn read_header_unchecked(path: &Path) -> io::Result<FileHeader> {
+    cfg_if! {
+        if #[cfg(feature = "untrusted_fs")] {
+            use crate::fs;
+        } else {
+            use crate::untrusted::fs;
+        }
+    }
+
+    let mut buf = [0_u8; META_DATA_PLAIN_SIZE];
+    fs::File::open(path)?.read_exact(&mut buf)?;
+    FileHeader::parse(&buf)
+}
+
+// Explains a failed open when the file exists but has an unsupported format
+// version or was created with the other kind of key, which the library would
+// otherwise report as EINVAL or a MAC mismatch.
+fn open_error(path: &Path, opts: &OpenOptions, user_key: bool) -> Option<Error> {
+    // "w" truncates, so the previous file does not matter.
+    if opts.write {
+        return None;
+    }
+    let header = read_header_unchecked(path).ok()?;
+    if let Err(err) = header.check_version() {
+        return Some(err);
+    }
+    match (header.use_user_kdk_key, user_key) {
+        (true, false) => Some(io::const_io_error!(
+            io::ErrorKind::InvalidInput,
+            "protected file was created with a user key, use open_ex",
+        )),
+        (false, true) => Some(io::const_io_error!(
+            io::ErrorKind::InvalidInput,
+            "protected file was created with an automatic key, use open",
+        )),
+        _ => None,
+    }
+}
+
+// Runs the full integrity sweep asked for by verify_on_open. Files opened without
+// read access ("w" or "a") have no existing content that can be read back. Nothing
+// has been cached or written yet, so the sweep needs no cache clear and works for
+// "r+" as well. The drop hook is only installed on a handle that is returned.
+fn finish_open(mut file: SgxFile, opts: &OpenOptions) -> io::Result<SgxFile> {
+    if opts.verify_on_open && (opts.read || opts.update) {
+        file.sweep(|_| Ok(()))?;
+        file.clearerr();
+        file.seek(SeekFrom::Start(0))?;
+    }
+    file.writable = opts.write || opts.append || opts.update;
+    file.drop_error_hook = opts.drop_error_hook;
+    Ok(file)
+}
+
 fn cstr(path: &Path) -> io::Result<CString> {
     Ok(CString::new(path.as_os_str().as_bytes())?)
 }
 
 impl FromInner<SgxFileStream> for SgxFile {
     fn from_inner(stream: SgxFileStream) -> SgxFile {
-        SgxFile(stream)
+        SgxFile { stream, drop_error_hook: None, writable: true }
+    }
+}
+
+impl Drop for SgxFile {
+    fn drop(&mut self) {
+        // Closing the stream flushes it anyway, but the error would be lost.
+        if let Some(hook) = self.drop_error_hook {
+            if let Err(e) = self.flush() {
+                hook(&e);
+            }
+        }
     }
 }
 
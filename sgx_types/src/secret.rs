@@ -0,0 +1,87 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! A wrapper that keeps secret values out of formatted output and wipes them on drop.
+
+use crate::marker::ContiguousMemory;
+use crate::{sgx_key_128bit_t, sgx_key_256bit_t};
+use core::sync::atomic::{self, Ordering};
+use core::{fmt, mem, ptr};
+
+/// A secret value, such as a sgx_key_128bit_t.
+///
+/// Debug and Display print a placeholder instead of the value, so a key held in a struct
+/// that derives Debug does not end up in a log. The value itself is only reachable through
+/// expose and expose_mut. Secret does not implement PartialEq; compare keys with
+/// rsgx_key128_ct_eq from sgx_tcrypto instead.
+///
+/// When a Secret is dropped, its bytes are overwritten with zeros by volatile writes that
+/// the compiler cannot remove. Copies taken out through expose are not covered.
+#[derive(Clone, Default)]
+#[repr(transparent)]
+pub struct Secret<T: Copy + ContiguousMemory>(T);
+
+/// A 128-bit key that is redacted in formatted output and zeroed on drop.
+pub type SecretKey128 = Secret<sgx_key_128bit_t>;
+
+/// A 256-bit key that is redacted in formatted output and zeroed on drop.
+pub type SecretKey256 = Secret<sgx_key_256bit_t>;
+
+impl<T: Copy + ContiguousMemory> Secret<T> {
+    /// Wraps a secret value.
+    pub fn new(value: T) -> Secret<T> {
+        Secret(value)
+    }
+
+    /// Returns a reference to the secret value.
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    /// Returns a mutable reference to the secret value, e.g. to fill it in place.
+    pub fn expose_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Copy + ContiguousMemory> From<T> for Secret<T> {
+    fn from(value: T) -> Secret<T> {
+        Secret(value)
+    }
+}
+
+impl<T: Copy + ContiguousMemory> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(****)")
+    }
+}
+
+impl<T: Copy + ContiguousMemory> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("****")
+    }
+}
+
+impl<T: Copy + ContiguousMemory> Drop for Secret<T> {
+    fn drop(&mut self) {
+        let bytes = &mut self.0 as *mut T as *mut u8;
+        for i in 0..mem::size_of::<T>() {
+            unsafe { ptr::write_volatile(bytes.add(i), 0) };
+        }
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
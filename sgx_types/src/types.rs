@@ -208,6 +208,94 @@ impl_struct! {
     }
 }
 
+impl TryFrom<&[u8]> for sgx_cpu_svn_t {
+    type Error = sgx_status_t;
+
+    fn try_from(bytes: &[u8]) -> SgxResult<sgx_cpu_svn_t> {
+        bytes
+            .try_into()
+            .map(|svn| sgx_cpu_svn_t { svn })
+            .map_err(|_| sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    }
+}
+
+impl sgx_cpu_svn_t {
+    pub fn as_bytes(&self) -> &[uint8_t; SGX_CPUSVN_SIZE] {
+        &self.svn
+    }
+
+    /// Returns true if every component of `self` is greater than or equal to the
+    /// corresponding component of `other`.
+    ///
+    /// CPUSVN is made of independent one-byte TCB components and is only partially
+    /// ordered, the same way the TCB level of a platform is matched in TCB info: if
+    /// some components are higher and others lower, neither value is at least the
+    /// other. A key request with `cpu_svn` set to `other` can only succeed on a
+    /// platform whose CPUSVN `is_at_least(other)`.
+    pub fn is_at_least(&self, other: &sgx_cpu_svn_t) -> bool {
+        self.svn.iter().zip(other.svn.iter()).all(|(a, b)| a >= b)
+    }
+}
+
+impl TryFrom<&[u8]> for sgx_key_id_t {
+    type Error = sgx_status_t;
+
+    fn try_from(bytes: &[u8]) -> SgxResult<sgx_key_id_t> {
+        bytes
+            .try_into()
+            .map(|id| sgx_key_id_t { id })
+            .map_err(|_| sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    }
+}
+
+impl From<[uint8_t; SGX_CPUSVN_SIZE]> for sgx_cpu_svn_t {
+    fn from(svn: [uint8_t; SGX_CPUSVN_SIZE]) -> sgx_cpu_svn_t {
+        sgx_cpu_svn_t { svn }
+    }
+}
+
+impl From<sgx_cpu_svn_t> for [uint8_t; SGX_CPUSVN_SIZE] {
+    fn from(cpu_svn: sgx_cpu_svn_t) -> [uint8_t; SGX_CPUSVN_SIZE] {
+        cpu_svn.svn
+    }
+}
+
+impl AsRef<[uint8_t]> for sgx_cpu_svn_t {
+    fn as_ref(&self) -> &[uint8_t] {
+        &self.svn
+    }
+}
+
+impl From<[uint8_t; SGX_KEYID_SIZE]> for sgx_key_id_t {
+    fn from(id: [uint8_t; SGX_KEYID_SIZE]) -> sgx_key_id_t {
+        sgx_key_id_t { id }
+    }
+}
+
+impl From<sgx_key_id_t> for [uint8_t; SGX_KEYID_SIZE] {
+    fn from(key_id: sgx_key_id_t) -> [uint8_t; SGX_KEYID_SIZE] {
+        key_id.id
+    }
+}
+
+impl AsRef<[uint8_t]> for sgx_key_id_t {
+    fn as_ref(&self) -> &[uint8_t] {
+        &self.id
+    }
+}
+
+impl AsMut<[uint8_t]> for sgx_cpu_svn_t {
+    fn as_mut(&mut self) -> &mut [uint8_t] {
+        &mut self.svn
+    }
+}
+
+impl AsMut<[uint8_t]> for sgx_key_id_t {
+    fn as_mut(&mut self) -> &mut [uint8_t] {
+        &mut self.id
+    }
+}
+
 impl_copy_clone! {
     pub struct sgx_key_request_t {
         pub key_name: uint16_t,
@@ -231,6 +319,10 @@ impl_struct_ContiguousMemory! {
     sgx_key_request_t;
 }
 
+// EGETKEY reads the request as a 512-byte KEYREQUEST. Any change to this layout changes
+// every derived key, including the keys of existing sealed and protected files.
+const _: () = assert!(core::mem::size_of::<sgx_key_request_t>() == 512);
+
 //
 // sgx_key_exchange.h
 //
@@ -495,6 +587,11 @@ impl_struct_ContiguousMemory! {
     sgx_report_t;
 }
 
+// Fixed by EREPORT (TARGETINFO and REPORT).
+const _: () = assert!(core::mem::size_of::<sgx_target_info_t>() == 512);
+const _: () = assert!(core::mem::size_of::<sgx_report_body_t>() == 384);
+const _: () = assert!(core::mem::size_of::<sgx_report_t>() == 432);
+
 /* intel sgx sdk 2.16 */
 //
 // sgx_report2.h
@@ -647,6 +744,7 @@ pub const SGX_MC_POLICY_ENCLAVE: uint16_t  = 0x02;
 pub const SGX_SHA1_HASH_SIZE: size_t = 20;
 pub const SGX_SHA256_HASH_SIZE: size_t = 32;
 pub const SGX_SHA384_HASH_SIZE: size_t = 48;
+pub const SGX_SHA512_HASH_SIZE: size_t = 64;
 pub const SGX_ECP256_KEY_SIZE: size_t = 32;
 pub const SGX_NISTP_ECP256_KEY_SIZE: size_t = SGX_ECP256_KEY_SIZE / 4;
 pub const SGX_AESGCM_IV_SIZE: size_t = 12;
@@ -737,6 +835,7 @@ pub type sgx_aes_state_handle_t = *mut c_void;
 pub type sgx_sha1_hash_t = [uint8_t; SGX_SHA1_HASH_SIZE];
 pub type sgx_sha256_hash_t = [uint8_t; SGX_SHA256_HASH_SIZE];
 pub type sgx_sha384_hash_t = [uint8_t; SGX_SHA384_HASH_SIZE];
+pub type sgx_sha512_hash_t = [uint8_t; SGX_SHA512_HASH_SIZE];
 
 pub type sgx_aes_gcm_128bit_key_t = [uint8_t; SGX_AESGCM_KEY_SIZE];
 pub type sgx_aes_gcm_128bit_tag_t = [uint8_t; SGX_AESGCM_MAC_SIZE];
@@ -1175,6 +1274,30 @@ pub type SGX_FILE = *mut c_void;
 pub const FILENAME_MAX: c_uint = 260; //define in sgx_tprotected_fs.h
 pub const FOPEN_MAX: c_uint = 20; //define in sgx_tprotected_fs.h
 
+// kdf_input_t in Intel's libsgx_tprotected_fs, the input of its AES-128-CMAC key derivation.
+// index at 0, label at 4, node_number at 68, nonce at 76, output_len (in bits) at 108.
+pub const SGX_PFS_KDF_LABEL_MAX_LEN: size_t = 64;
+
+impl_packed_copy_clone! {
+    pub struct sgx_pfs_kdf_input_t {
+        pub index: uint32_t,
+        pub label: [uint8_t; SGX_PFS_KDF_LABEL_MAX_LEN],
+        pub node_number: uint64_t,
+        pub nonce: sgx_key_id_t,
+        pub output_len: uint32_t,
+    }
+}
+
+impl_struct_default! {
+    sgx_pfs_kdf_input_t; //112
+}
+
+impl_struct_ContiguousMemory! {
+    sgx_pfs_kdf_input_t;
+}
+
+const _: () = assert!(core::mem::size_of::<sgx_pfs_kdf_input_t>() == 112);
+
 /* intel sgx sdk 2.0 */
 //
 // sgx_capable.h